@@ -0,0 +1,120 @@
+use crate::command::{CommandInjector, CommandPlugin};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use regex::Regex;
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+use tracing::debug;
+
+/// 服务端结构化事件
+#[derive(Clone, Debug)]
+pub enum ServerEvent {
+    /// 玩家加入
+    PlayerJoined { name: String },
+    /// 玩家离开
+    PlayerLeft { name: String },
+    /// 聊天消息
+    #[allow(dead_code, reason = "提供给订阅者，内置功能暂未使用")]
+    Chat { name: String, message: String },
+    /// 服务端启动完成
+    ServerDone,
+    /// 玩家死亡
+    #[allow(dead_code, reason = "提供给订阅者，内置功能暂未使用")]
+    PlayerDeath { name: String, message: String },
+}
+
+/// 带有实例 id 的事件
+#[derive(Clone, Debug)]
+pub struct InstanceEvent {
+    /// 产生事件的实例，与注册备份计划时的 id 相同
+    pub id: usize,
+    pub event: ServerEvent,
+}
+
+/// 事件广播，通过 subscribe 获取事件，所有实例共用
+pub static SERVER_EVENTS: LazyLock<broadcast::Sender<InstanceEvent>> =
+    LazyLock::new(|| broadcast::channel(256).0);
+
+/// 默认匹配规则，适用于 Vanilla 和 Paper 的日志格式
+/// 捕获组 name 为玩家名称，message 为消息内容
+const DEFAULT_PATTERNS: &[(&str, &str)] = &[
+    ("join", r"]: (?P<name>\w+) joined the game$"),
+    ("leave", r"]: (?P<name>\w+) left the game$"),
+    (
+        "chat",
+        r"]: (?:\[Not Secure] )?<(?P<name>[^>]+)> (?P<message>.*)$",
+    ),
+    ("done", r"]: Done \([0-9.]+s\)!"),
+    (
+        "death",
+        r"]: (?P<name>\w+) (?P<message>(?:was|fell|drowned|died|burned|blew up|hit the ground|starved|suffocated|froze|withered|experienced|went up in flames|tried to swim|walked into|discovered)\b.*)$",
+    ),
+];
+
+/// 从输出中解析结构化事件
+pub struct ServerEventPlugin {
+    /// 事件所属的实例
+    instance: usize,
+    patterns: Vec<(&'static str, Regex)>,
+}
+
+impl ServerEventPlugin {
+    /// 创建插件，settings 中同名的键会覆盖默认规则
+    pub fn new(instance: usize, settings: &toml::Table) -> Result<Self> {
+        let patterns = DEFAULT_PATTERNS
+            .iter()
+            .map(|(kind, default)| {
+                let pattern = match settings.get(*kind) {
+                    None => *default,
+                    Some(v) => v
+                        .as_str()
+                        .ok_or_else(|| anyhow!("Event pattern \"{kind}\" must be a string"))?,
+                };
+                Ok((*kind, Regex::new(pattern)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { instance, patterns })
+    }
+
+    pub(crate) fn parse(&self, line: &str) -> Option<ServerEvent> {
+        for (kind, re) in &self.patterns {
+            let Some(c) = re.captures(line) else {
+                continue;
+            };
+            let get = |key: &str| {
+                c.name(key)
+                    .map(|m| m.as_str().to_string())
+                    .unwrap_or_default()
+            };
+            return Some(match *kind {
+                "join" => ServerEvent::PlayerJoined { name: get("name") },
+                "leave" => ServerEvent::PlayerLeft { name: get("name") },
+                "chat" => ServerEvent::Chat {
+                    name: get("name"),
+                    message: get("message"),
+                },
+                "done" => ServerEvent::ServerDone,
+                _ => ServerEvent::PlayerDeath {
+                    name: get("name"),
+                    message: get("message"),
+                },
+            });
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl CommandPlugin for ServerEventPlugin {
+    async fn process(&self, value: String, _sender: &CommandInjector) -> Option<String> {
+        if let Some(event) = self.parse(&value) {
+            debug!("Server event: {:?}", event);
+            // 没有订阅者时忽略
+            let _ = SERVER_EVENTS.send(InstanceEvent {
+                id: self.instance,
+                event,
+            });
+        }
+        Some(value)
+    }
+}
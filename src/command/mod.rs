@@ -1,3 +1,5 @@
+pub mod event;
+pub mod oom;
 pub mod raw;
 
 use std::collections::HashMap;
@@ -5,18 +7,84 @@ use std::sync::Arc;
 
 use tokio::select;
 use tokio::sync::Mutex;
-use tokio::sync::mpsc::{Receiver, Sender, channel};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
 
 use crate::TASK_MANAGER;
 use crate::core::mc_server::runner::Runner;
+use crate::util::channel::{PolicyReceiver, PolicySender, policy_channel};
 use anyhow::{Result, anyhow};
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
-use futures::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+
+/// 插件向服务端注入命令的通道
+/// 发送不会阻塞输出管道，命令由独立的任务转发到服务端的标准输入。
+/// 因此注入的命令不会在同一次 process 调用中生效，其输出会在之后的行中到达；
+/// 插件不应在 process 中等待自己注入的命令的结果，否则会阻塞整个管道
+pub type CommandInjector = UnboundedSender<String>;
 
 #[async_trait]
 pub trait CommandPlugin: Send + Sync {
-    async fn process(&self, value: String, sender: Arc<Sender<String>>) -> String;
+    /// 处理一行输出，返回 None 时丢弃该行，后续插件不再处理
+    async fn process(&self, value: String, sender: &CommandInjector) -> Option<String>;
+}
+
+/// 命令插件配置
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CommandPluginCfg {
+    /// 插件名称
+    pub name: String,
+    /// 插件设置
+    #[serde(default)]
+    pub settings: toml::Table,
+}
+
+/// 未配置时默认启用的插件，server_event 提供启动完成和玩家活动事件
+pub fn default_plugins() -> Vec<CommandPluginCfg> {
+    ["example", "server_event"]
+        .into_iter()
+        .map(|name| CommandPluginCfg {
+            name: name.to_string(),
+            settings: Default::default(),
+        })
+        .collect()
+}
+
+/// 参数为实例 id 和插件设置
+type PluginConstructor = fn(usize, &toml::Table) -> Result<Box<dyn CommandPlugin>>;
+
+/// 内置插件注册表
+const PLUGIN_REGISTRY: &[(&str, PluginConstructor)] = &[
+    ("example", |_, _| Ok(Box::new(raw::ExamplePlugin))),
+    ("auto_restart_on_oom", |_, _| {
+        Ok(Box::new(oom::AutoRestartOnOomPlugin::new()))
+    }),
+    ("server_event", |instance, settings| {
+        Ok(Box::new(event::ServerEventPlugin::new(instance, settings)?))
+    }),
+];
+
+/// 根据配置为实例创建插件
+pub fn build_plugins(
+    instance: usize,
+    cfg: &[CommandPluginCfg],
+) -> Result<Vec<Box<dyn CommandPlugin>>> {
+    cfg.iter()
+        .map(
+            |c| match PLUGIN_REGISTRY.iter().find(|(name, _)| *name == c.name) {
+                Some((_, constructor)) => constructor(instance, &c.settings),
+                None => Err(anyhow!(
+                    "Unknown command plugin: {}, available: {}",
+                    c.name,
+                    PLUGIN_REGISTRY
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
+        )
+        .collect()
 }
 
 pub struct CommandLoader {
@@ -50,10 +118,9 @@ impl CommandLoader {
         }
     }
     /// 将实例加载到命令插件加载器，并返回处理过的 Receiver，此操作会阻塞原有 Receiver，需要输出应使用返回的 Receiver
-    pub async fn load(&mut self, runner: &Runner) -> Result<Arc<Mutex<Receiver<String>>>> {
-        let (tx, rx) = channel::<String>(32);
+    pub async fn load(&mut self, runner: &Runner) -> Result<Arc<Mutex<PolicyReceiver<String>>>> {
+        let (tx, rx) = policy_channel::<String>(runner.channels.output);
         let tx = Arc::new(tx);
-        let rx = Arc::new(Mutex::new(rx));
         let input = Arc::clone(&runner.input);
         let output = Arc::clone(&runner.output);
 
@@ -69,20 +136,40 @@ impl CommandLoader {
             Some(p) => p.load(),
         };
 
+        // 插件注入的命令 -> 服务端
+        let (inject_tx, mut inject_rx) = unbounded_channel::<String>();
+        TASK_MANAGER
+            .spawn_with_cancel(async move |t| {
+                loop {
+                    select! {
+                        Some(command) = inject_rx.recv() => input.send(command).await?,
+                        _ = t.cancelled() => break
+                    }
+                }
+                Ok(())
+            })
+            .await?;
+
         async fn pipeline(
-            input: Arc<Sender<String>>,
-            output: Arc<Mutex<Receiver<String>>>,
-            tx: Arc<Sender<String>>,
+            injector: CommandInjector,
+            output: Arc<Mutex<PolicyReceiver<String>>>,
+            tx: Arc<PolicySender<String>>,
             plugins: Arc<Vec<Box<dyn CommandPlugin>>>,
         ) -> Result<()> {
             match output.lock().await.recv().await {
                 None => Err(anyhow!("channel closed")),
                 Some(value) => {
-                    let value = stream::iter(plugins.iter())
-                        .fold(value, |v, x: &Box<dyn CommandPlugin>| {
-                            x.process(v, input.clone())
-                        })
-                        .await;
+                    // 依次经过插件处理，任一插件返回 None 时丢弃该行
+                    let mut value = Some(value);
+                    for plugin in plugins.iter() {
+                        value = match value {
+                            Some(v) => plugin.process(v, &injector).await,
+                            None => break,
+                        };
+                    }
+                    let Some(value) = value else {
+                        return Ok(());
+                    };
                     match tx.send(value).await {
                         Err(e) => Err(e.into()),
                         Ok(_) => Ok(()),
@@ -95,7 +182,12 @@ impl CommandLoader {
             .spawn_with_cancel(async move |t| {
                 loop {
                     select! {
-                        e = pipeline(input.clone(),output.clone(),tx.clone(),plugins.clone()) => e?,
+                        e = pipeline(
+                            inject_tx.clone(),
+                            output.clone(),
+                            tx.clone(),
+                            plugins.clone(),
+                        ) => e?,
                         _ = t.cancelled() => break
                     }
                 }
@@ -106,3 +198,68 @@ impl CommandLoader {
         Ok(rx)
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::core::mc_server::mock::MockServer;
+    use crate::core::mc_server::runner::ChannelsCfg;
+    use crate::util::testing::block_on;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// 隐藏含 secret 的行，启动完成时注入一条命令
+    struct TestPlugin;
+
+    #[async_trait]
+    impl CommandPlugin for TestPlugin {
+        async fn process(&self, value: String, sender: &CommandInjector) -> Option<String> {
+            if value.contains("Done") {
+                sender.send("secret\n".to_string()).unwrap();
+                sender.send("say hi\n".to_string()).unwrap();
+            }
+            (!value.contains("secret")).then(|| value.to_uppercase())
+        }
+    }
+
+    #[test]
+    fn pipeline_processes_output() {
+        block_on(async {
+            let server = MockServer::with_stop_delay(Path::new("server.jar"), Duration::ZERO);
+            let runner = Runner::spawn_server(&server, ChannelsCfg::default())
+                .await
+                .unwrap();
+            let mut loader = CommandLoader::new();
+            loader
+                .register(runner.id, vec![Box::new(TestPlugin)])
+                .unwrap();
+            let output = loader.load(&runner).await.unwrap();
+
+            let mut lines = Vec::new();
+            let mut output = output.lock().await;
+            while let Some(line) = output.recv().await {
+                let done = line == "> SAY HI";
+                lines.push(line);
+                if done {
+                    break;
+                }
+            }
+            drop(output);
+            // 注入的 secret 被服务端回显后仍会经过插件，因此不会出现
+            assert_eq!(
+                lines,
+                [
+                    "[SERVER THREAD/INFO]: STARTING MINECRAFT SERVER",
+                    r#"[SERVER THREAD/INFO]: DONE (0.1S)! FOR HELP, TYPE "HELP""#,
+                    "> SAY HI",
+                ]
+            );
+
+            runner
+                .kill_with_timeout(Some(Duration::from_secs(5)))
+                .await
+                .unwrap();
+            runner.wait().await.unwrap();
+        })
+    }
+}
@@ -0,0 +1,34 @@
+use crate::command::{CommandInjector, CommandPlugin};
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, warn};
+
+/// 检测到内存溢出时保存世界并停止服务端
+pub struct AutoRestartOnOomPlugin {
+    triggered: AtomicBool,
+}
+
+impl AutoRestartOnOomPlugin {
+    pub fn new() -> Self {
+        Self {
+            triggered: AtomicBool::new(false),
+        }
+    }
+}
+
+#[async_trait]
+impl CommandPlugin for AutoRestartOnOomPlugin {
+    async fn process(&self, value: String, sender: &CommandInjector) -> Option<String> {
+        if value.contains("java.lang.OutOfMemoryError")
+            && !self.triggered.swap(true, Ordering::Relaxed)
+        {
+            warn!("OutOfMemoryError detected, saving and stopping the server");
+            for command in ["save-all\n", "stop\n"] {
+                if let Err(e) = sender.send(command.to_string()) {
+                    error!("{e}")
+                }
+            }
+        }
+        Some(value)
+    }
+}
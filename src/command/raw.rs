@@ -1,15 +1,13 @@
-use crate::command::CommandPlugin;
+use crate::command::{CommandInjector, CommandPlugin};
 use async_trait::async_trait;
 use serde_json::json;
-use std::sync::Arc;
-use tokio::sync::mpsc::Sender;
 use tracing::error;
 
 pub struct ExamplePlugin;
 
 #[async_trait]
 impl CommandPlugin for ExamplePlugin {
-    async fn process(&self, value: String, sender: Arc<Sender<String>>) -> String {
+    async fn process(&self, value: String, sender: &CommandInjector) -> Option<String> {
         let re = regex::Regex::new(
             r#"^\[(?P<time>\d{2}:\d{2}:\d{2})] \[(?P<thread>[^/]+)/(?P<level>[^]]+)]: <(?P<user>[^>]+)> (?P<message>.+)$"#
         ).unwrap();
@@ -25,7 +23,7 @@ impl CommandPlugin for ExamplePlugin {
                     .to_string(),
                 );
 
-                match sender.send(msg).await {
+                match sender.send(msg) {
                     Ok(_) => {}
                     Err(e) => {
                         error!("{e}")
@@ -33,6 +31,6 @@ impl CommandPlugin for ExamplePlugin {
                 }
             }
         }
-        value
+        Some(value)
     }
 }
@@ -0,0 +1,76 @@
+use crate::GLOBAL_CACHE;
+use crate::core::arguments::export::EXCLUDE;
+use crate::core::config::project::McServerConfig;
+use crate::util::lock::PidLock;
+use crate::util::properties;
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// 立即创建快照，默认备份配置的来源
+/// world_only 为 true 时只备份 server.properties 中的世界目录，all 为 true 时备份整个项目目录
+pub async fn now(world_only: bool, all: bool) -> Result<()> {
+    let cfg = McServerConfig::current()
+        .await
+        .ok_or(anyhow!("The configuration file was not found"))?;
+
+    // 无法通知另一个进程中的服务端暂停保存，只能提示
+    let _lock = match PidLock::acquire(&Path::new(".toymine").join("run.lock")) {
+        Ok(lock) => Some(lock),
+        Err(_) => {
+            warn!(
+                "The server is running, the snapshot may contain partially saved chunks, run save-off and save-all in its console first"
+            );
+            None
+        }
+    };
+
+    let mut backup = cfg.backup.clone();
+    let tag = if all {
+        backup.set_source(project_files()?);
+        "Manual all"
+    } else if world_only {
+        backup.set_source(world_files().await?);
+        "Manual world"
+    } else {
+        "Manual"
+    };
+    let id = backup.snapshot(&GLOBAL_CACHE, tag).await?;
+    println!("{id}");
+    Ok(())
+}
+
+/// server.properties 中的世界目录
+async fn world_files() -> Result<Vec<PathBuf>> {
+    let props = match tokio::fs::read_to_string("server.properties").await {
+        Ok(s) => properties::parse(&s),
+        Err(_) => Vec::new(),
+    };
+    let worlds: Vec<PathBuf> = properties::world_dirs(&props)
+        .into_iter()
+        .filter(|p| p.is_dir())
+        .collect();
+    if worlds.is_empty() {
+        return Err(anyhow!(
+            "World \"{}\" was not found",
+            properties::level_name(&props)
+        ));
+    }
+    Ok(worlds)
+}
+
+/// 项目目录中除备份仓库、日志和缓存外的内容
+fn project_files() -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(".")? {
+        let path = entry?.path();
+        let excluded = path
+            .file_name()
+            .is_some_and(|n| EXCLUDE.iter().any(|e| n == *e));
+        if !excluded {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
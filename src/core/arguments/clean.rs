@@ -0,0 +1,124 @@
+use crate::runtime::java::java_binary;
+use crate::{GLOBAL_CACHE, GLOBAL_RUNTIME, RUN_CACHE};
+use anyhow::Result;
+use indicatif::HumanBytes;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::warn;
+
+/// 仍可能被其他进程使用的下载目录的保留时间
+const RUN_CACHE_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 清理的类别
+pub struct CleanOptions {
+    pub logs: bool,
+    pub cache: bool,
+    /// 清理早于指定天数的 .bak 文件
+    pub backups_older_than: Option<u64>,
+    pub runtimes: bool,
+    pub dry_run: bool,
+}
+
+pub async fn clean(mut options: CleanOptions) -> Result<()> {
+    // 未指定类别时清理日志、缓存和运行时
+    if !options.logs && !options.cache && !options.runtimes && options.backups_older_than.is_none()
+    {
+        options.logs = true;
+        options.cache = true;
+        options.runtimes = true;
+    }
+    tokio::task::spawn_blocking(move || clean_blocking(&options)).await?
+}
+
+fn clean_blocking(options: &CleanOptions) -> Result<()> {
+    let mut targets: Vec<(&str, PathBuf)> = Vec::new();
+    if options.logs {
+        // 只清理归档的日志，保留 latest.log
+        for path in read_dir(Path::new("logs")) {
+            if path.extension().is_some_and(|e| e == "gz") {
+                targets.push(("log", path));
+            }
+        }
+    }
+    if options.cache {
+        for path in read_dir(&GLOBAL_CACHE) {
+            if path == *RUN_CACHE {
+                continue;
+            }
+            // 其他进程的下载目录可能仍在使用
+            let is_run = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("run-"));
+            if is_run && age(&path).is_none_or(|a| a < RUN_CACHE_GRACE) {
+                continue;
+            }
+            targets.push(("cache", path));
+        }
+    }
+    if let Some(days) = options.backups_older_than {
+        let limit = Duration::from_secs(days * 24 * 60 * 60);
+        for path in read_dir(Path::new(".")) {
+            if path.is_file()
+                && path.extension().is_some_and(|e| e == "bak")
+                && age(&path).is_some_and(|a| a >= limit)
+            {
+                targets.push(("bak", path));
+            }
+        }
+    }
+    if options.runtimes {
+        // 没有 java 可执行文件的运行时视为安装失败的残留
+        for path in read_dir(&GLOBAL_RUNTIME) {
+            if path.is_dir() && java_binary(&path).is_none() {
+                targets.push(("runtime", path));
+            }
+        }
+    }
+
+    let mut total = 0;
+    for (kind, path) in &targets {
+        let size = size_of(path);
+        total += size;
+        println!("{:<8} {:>10}  {}", kind, HumanBytes(size), path.display());
+        if options.dry_run {
+            continue;
+        }
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        };
+        if let Err(e) = result {
+            warn!("Failed to remove {}: {e}", path.display());
+        }
+    }
+    if options.dry_run {
+        println!("{} would be freed", HumanBytes(total));
+    } else {
+        println!("{} freed", HumanBytes(total));
+    }
+    Ok(())
+}
+
+/// 列出目录内容，目录不存在时为空
+fn read_dir(path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(path) {
+        Ok(rd) => rd.flatten().map(|e| e.path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 距离最后修改的时间
+fn age(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+/// 文件或目录的总大小，不跟随符号链接
+fn size_of(path: &Path) -> u64 {
+    match std::fs::symlink_metadata(path) {
+        Ok(m) if m.is_dir() => read_dir(path).iter().map(|p| size_of(p)).sum(),
+        Ok(m) => m.len(),
+        Err(_) => 0,
+    }
+}
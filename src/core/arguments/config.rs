@@ -0,0 +1,115 @@
+use crate::core::config::project::McServerConfig;
+use crate::versions::VersionManager;
+use anyhow::{Result, anyhow};
+use std::path::Path;
+use toml::{Table, Value};
+use tracing::info;
+
+/// 读取配置项，key 为点分隔的路径，例如 `backup.option.on_stop`
+pub async fn get(key: &str) -> Result<()> {
+    let table = load().await?;
+    let value = lookup(&table, key).ok_or_else(|| anyhow!("Unknown key: {key}"))?;
+    match value {
+        Value::String(s) => println!("{s}"),
+        Value::Table(t) => print!("{}", toml::to_string(t)?),
+        v => println!("{v}"),
+    }
+    Ok(())
+}
+
+/// 修改配置项，校验通过后写回
+pub async fn set(key: &str, value: &str) -> Result<()> {
+    let mut table = load().await?;
+    let new = parse_value(value, lookup(&table, key))?;
+    if let Some(old) = lookup(&table, key)
+        && std::mem::discriminant(old) != std::mem::discriminant(&new)
+    {
+        return Err(anyhow!(
+            "Type mismatch for {key}: expected {}, got {}",
+            old.type_str(),
+            new.type_str()
+        ));
+    }
+    *lookup_mut(&mut table, key)? = new.clone();
+
+    // 以类型化配置校验，包括服务端的内部配置
+    let text = toml::to_string(&table)?;
+    let cfg: McServerConfig = text
+        .parse()
+        .map_err(|e| anyhow!("Invalid value for {key}: {e}"))?;
+    let mut server = VersionManager::from_cfg(&cfg).ok_or(anyhow!("MC Server Not Found"))?;
+    McServerConfig::load_from_str(&text, server.as_mut())
+        .map_err(|e| anyhow!("Invalid value for {key}: {e}"))?;
+    cfg.project.check_server_file()?;
+
+    // 配置中不存在的键在重新序列化后会丢失
+    let saved: Table = toml::from_str(&cfg.to_string(server.as_ref())?)?;
+    if lookup(&saved, key) != Some(&new) {
+        return Err(anyhow!("Unknown key: {key}"));
+    }
+    cfg.save(server.as_ref()).await?;
+    info!("{key} = {new}");
+    Ok(())
+}
+
+async fn load() -> Result<Table> {
+    let path = Path::new("ToyMine.toml");
+    if !path.exists() {
+        return Err(anyhow!("The configuration file was not found"));
+    }
+    Ok(toml::from_str(&tokio::fs::read_to_string(path).await?)?)
+}
+
+/// 按 TOML 字面量解析，无法解析或原值为字符串时视为字符串
+fn parse_value(value: &str, old: Option<&Value>) -> Result<Value> {
+    if let Some(Value::String(_)) = old {
+        return Ok(Value::String(value.to_string()));
+    }
+    match toml::from_str::<Table>(&format!("v = {value}")) {
+        Ok(mut t) => t.remove("v").ok_or(anyhow!("Invalid value: {value}")),
+        Err(_) => Ok(Value::String(value.to_string())),
+    }
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            Value::Table(t) => t.get(part)?,
+            Value::Array(a) => a.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// 获取可修改的配置项，最后一级不存在时在表中创建
+fn lookup_mut<'a>(table: &'a mut Table, key: &str) -> Result<&'a mut Value> {
+    let unknown = || anyhow!("Unknown key: {key}");
+    let (parents, last) = match key.rsplit_once('.') {
+        Some((parents, last)) => (Some(parents), last),
+        None => (None, key),
+    };
+    let parent = match parents {
+        None => table,
+        Some(parents) => {
+            let mut parts = parents.split('.');
+            let mut value = table
+                .get_mut(parts.next().ok_or_else(unknown)?)
+                .ok_or_else(unknown)?;
+            for part in parts {
+                value = match value {
+                    Value::Table(t) => t.get_mut(part),
+                    Value::Array(a) => part.parse::<usize>().ok().and_then(|i| a.get_mut(i)),
+                    _ => None,
+                }
+                .ok_or_else(unknown)?;
+            }
+            value.as_table_mut().ok_or_else(unknown)?
+        }
+    };
+    Ok(parent
+        .entry(last.to_string())
+        .or_insert(Value::Boolean(false)))
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// 不打包的内容：备份仓库、历史核心、日志和可重新生成的缓存
+pub(crate) const EXCLUDE: &[&str] = &[
+    ".toymine",
+    "logs",
+    "crash-reports",
+    "cache",
+    "libraries",
+    "versions",
+];
+
+/// zstd 压缩等级
+const LEVEL: i32 = 9;
+
+pub async fn export(out: PathBuf) -> Result<()> {
+    if !Path::new("ToyMine.toml").exists() {
+        return Err(anyhow!("The configuration file was not found"));
+    }
+    let file =
+        File::create_new(&out).with_context(|| format!("Failed to create {}", out.display()))?;
+    let result = tokio::task::spawn_blocking({
+        let out = out.clone();
+        move || pack(file, &out)
+    })
+    .await?;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&out).await;
+    }
+    result?;
+    info!("Project exported to {}", out.display());
+    Ok(())
+}
+
+/// 以流的方式打包当前目录，不在内存中缓存文件
+fn pack(file: File, out: &Path) -> Result<()> {
+    // 输出文件位于项目目录中时跳过自身
+    let out = out.canonicalize()?;
+    let encoder = zstd::Encoder::new(file, LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+
+    for entry in std::fs::read_dir(".")? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let path = entry.path();
+        if EXCLUDE.iter().any(|e| name == *e)
+            || path.extension().is_some_and(|e| e == "bak")
+            || path.canonicalize().is_ok_and(|p| p == out)
+        {
+            debug!("Skipping {}", path.display());
+            continue;
+        }
+        debug!("Adding {}", path.display());
+        if entry.file_type()?.is_dir() {
+            builder.append_dir_all(&name, &path)?;
+        } else {
+            builder.append_path_with_name(&path, &name)?;
+        }
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// 解压项目归档到目标目录
+pub async fn unpack(archive: &Path, target: &Path) -> Result<()> {
+    if target.exists() && std::fs::read_dir(target)?.next().is_some() {
+        return Err(anyhow!("{} is not empty", target.display()));
+    }
+    let file =
+        File::open(archive).with_context(|| format!("Failed to open {}", archive.display()))?;
+    let target = target.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&target)?;
+        let decoder = zstd::Decoder::new(file)?;
+        // unpack 会拒绝解压到目标目录之外的条目
+        tar::Archive::new(decoder).unpack(&target)?;
+        Ok::<(), anyhow::Error>(())
+    })
+    .await?
+}
@@ -0,0 +1,107 @@
+use crate::GLOBAL_CACHE;
+use crate::core::arguments::export;
+use crate::core::config::project::McServerConfig;
+use crate::util::hash::file_sha1;
+use crate::util::properties;
+use crate::versions::VersionManager;
+use anyhow::{Context, Result, anyhow};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+pub async fn import(path: PathBuf, name: Option<String>, into: Option<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        return import_archive(&path, into).await;
+    }
+
+    // 就地接管，不移动任何文件
+    let dir = path;
+    std::env::set_current_dir(&dir)
+        .with_context(|| format!("Failed to enter {}", dir.display()))?;
+    if Path::new("ToyMine.toml").exists() {
+        return Err(anyhow!("The configuration file already exists"));
+    }
+
+    let mut cfg = VersionManager::detect_config()?;
+    cfg.project.check_server_file()?;
+    if let Some(name) = name {
+        cfg.project.name = name;
+    }
+
+    // 从 server.properties 读取世界名称和描述
+    let props = match tokio::fs::read_to_string("server.properties").await {
+        Ok(s) => properties::parse(&s),
+        Err(_) => {
+            warn!("server.properties was not found, using the defaults");
+            Vec::new()
+        }
+    };
+    if let Some(motd) = properties::get(&props, "motd") {
+        cfg.project.description = motd.to_string();
+    }
+    let level = properties::level_name(&props);
+    let worlds: Vec<PathBuf> = properties::world_dirs(&props)
+        .into_iter()
+        .filter(|p| p.is_dir())
+        .collect();
+    if worlds.is_empty() {
+        warn!("World \"{level}\" was not found");
+        cfg.backup.set_source(vec![PathBuf::from(level)]);
+    } else {
+        cfg.backup.set_source(worlds.clone());
+    }
+
+    cfg.project.server_sha1 = Some(file_sha1(&cfg.project.server_file).await?);
+    let server = VersionManager::from_cfg(&cfg).ok_or(anyhow!("MC Server Not Found"))?;
+    cfg.save(server.as_ref()).await?;
+    info!(
+        "Project \"{}\" has been imported from {}",
+        cfg.project.name,
+        dir.display()
+    );
+
+    // 以当前世界创建第一个快照
+    if !worlds.is_empty() {
+        info!("Creating the initial backup");
+        if let Err(e) = cfg.backup.snapshot(&GLOBAL_CACHE, "Import").await {
+            warn!("Failed to create the initial backup: {e:#}");
+        }
+    }
+    Ok(())
+}
+
+/// 导入导出的归档，归档中的配置原样使用，不重新检测
+async fn import_archive(archive: &Path, into: Option<PathBuf>) -> Result<()> {
+    let target = match into {
+        Some(v) => v,
+        None => archive_stem(archive)?,
+    };
+    // 目标目录非空时 unpack 会拒绝
+    info!("Unpacking {} into {}", archive.display(), target.display());
+    export::unpack(archive, &target).await?;
+
+    std::env::set_current_dir(&target)
+        .with_context(|| format!("Failed to enter {}", target.display()))?;
+    let cfg = McServerConfig::open(Path::new("ToyMine.toml"))
+        .await
+        .context("The archive does not contain a valid ToyMine.toml")?;
+    cfg.project.check_server_file()?;
+    info!(
+        "Project \"{}\" has been imported into {}",
+        cfg.project.name,
+        target.display()
+    );
+    Ok(())
+}
+
+/// 由归档名称得到目录名，例如 survival.tar.zst -> survival
+fn archive_stem(archive: &Path) -> Result<PathBuf> {
+    let name = archive
+        .file_name()
+        .ok_or(anyhow!("Invalid archive path"))?
+        .to_string_lossy();
+    let stem = name
+        .strip_suffix(".tar.zst")
+        .or_else(|| name.strip_suffix(".tzst"))
+        .unwrap_or(&name);
+    Ok(PathBuf::from(stem))
+}
@@ -1,4 +1,79 @@
-use anyhow::Result;
+use crate::core::config::project::McServerConfig;
+use crate::core::mc_server::McType;
+use crate::util::nbt::{self, LevelInfo};
+use crate::util::{output, properties};
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::path::Path;
+use tracing::warn;
+
 pub async fn info() -> Result<()> {
+    let cfg = McServerConfig::current()
+        .await
+        .ok_or(anyhow!("The configuration file was not found"))?;
+    let project = &cfg.project;
+    // 基岩版的 level.dat 格式不同
+    let world = match project.version.server_type {
+        McType::Java(_) => read_world().await,
+        McType::Bedrock(_) => None,
+    };
+    if output::is_json() {
+        output::data(json!({
+            "name": project.name,
+            "description": project.description,
+            "version": project.version.compact(),
+            "server_file": project.server_file,
+            "server_sha1": project.server_sha1,
+            "creation_date": project.creation_date.to_rfc3339(),
+            "world": world.map(|w| json!({
+                "level_name": w.level_name,
+                "data_version": w.data_version,
+                "game_type": w.game_type.map(|g| g.name()),
+                "seed": w.seed,
+            })),
+        }));
+        return Ok(());
+    }
+    println!("Name:        {}", project.name);
+    println!("Description: {}", project.description);
+    println!("Version:     {}", project.version.compact());
+    println!("Server file: {}", project.server_file.display());
+    println!(
+        "Created:     {}",
+        project.creation_date.format("%Y-%m-%d %H:%M:%S")
+    );
+    if let Some(world) = world {
+        let unknown = || "unknown".to_string();
+        println!();
+        println!("World:       {}", world.level_name.unwrap_or_else(unknown));
+        println!(
+            "Data:        {}",
+            world.data_version.map_or_else(unknown, |v| v.to_string())
+        );
+        println!(
+            "Game mode:   {}",
+            world.game_type.map_or("unknown", |g| g.name())
+        );
+        println!(
+            "Seed:        {}",
+            world.seed.map_or_else(unknown, |s| s.to_string())
+        );
+    }
     Ok(())
 }
+
+/// 读取 server.properties 指定的世界，尚未生成时为 None
+async fn read_world() -> Option<LevelInfo> {
+    let props = tokio::fs::read_to_string("server.properties")
+        .await
+        .map(|s| properties::parse(&s))
+        .unwrap_or_default();
+    let level_dat = Path::new(properties::level_name(&props)).join("level.dat");
+    if tokio::fs::metadata(&level_dat).await.is_err() {
+        return None;
+    }
+    nbt::read_level_dat(&level_dat)
+        .await
+        .inspect_err(|e| warn!("Unable to read {}: {e}", level_dat.display()))
+        .ok()
+}
@@ -0,0 +1,44 @@
+use crate::util::hash::file_sha1;
+use crate::util::output;
+use crate::versions::VersionManager;
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+
+pub async fn init(name: Option<String>, description: Option<String>) -> Result<()> {
+    let path = Path::new("ToyMine.toml");
+    if path.exists() {
+        return Err(anyhow!("The configuration file already exists"));
+    }
+    // 从当前目录的服务端文件生成配置，参数优先于检测结果
+    let mut cfg = VersionManager::detect_config()?;
+    cfg.project.check_server_file()?;
+    if cfg.project.version.is_experimental() {
+        warn!(
+            "{} is an experimental version, worlds it has opened may not load in releases",
+            cfg.project.version.compact()
+        );
+    }
+    if let Some(name) = name {
+        cfg.project.name = name;
+    }
+    if let Some(description) = description {
+        cfg.project.description = description;
+    }
+    cfg.project.server_sha1 = Some(file_sha1(&cfg.project.server_file).await?);
+    let server = VersionManager::from_cfg(&cfg).ok_or(anyhow!("MC Server Not Found"))?;
+
+    let content = cfg.to_string(server.as_ref())?;
+
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(content.as_bytes()).await?;
+    file.flush().await?;
+    info!("Project \"{}\" has been created", cfg.project.name);
+    output::data(json!({
+        "name": cfg.project.name,
+        "version": cfg.project.version.compact(),
+    }));
+    Ok(())
+}
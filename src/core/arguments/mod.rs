@@ -1,2 +1,12 @@
+pub mod backup;
+pub mod clean;
+pub mod config;
+pub mod export;
+pub mod import;
 pub mod info;
+pub mod init;
+pub mod ping;
+pub mod player_list;
+pub mod rollback;
 pub mod start;
+pub mod upgrade;
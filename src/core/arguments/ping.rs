@@ -0,0 +1,71 @@
+use crate::util::{properties, slp};
+use anyhow::{Result, anyhow};
+use std::time::Duration;
+
+/// Java 版的默认端口
+const DEFAULT_PORT: u16 = 25565;
+
+/// 查询服务端状态，未指定地址时使用当前目录 server.properties 中的地址
+pub async fn ping(address: Option<String>) -> Result<()> {
+    let (host, port) = match address {
+        Some(address) => parse_address(&address)?,
+        None => local_address().await,
+    };
+    let status = slp::ping(&host, port, Duration::from_secs(5))
+        .await
+        .map_err(|e| {
+            e.context(format!(
+                "{host}:{port} did not respond to the status request"
+            ))
+        })?;
+    println!("MOTD:    {}", status.motd);
+    println!("Version: {} (protocol {})", status.version, status.protocol);
+    println!("Players: {}/{}", status.online, status.max);
+    println!("Latency: {} ms", status.latency.as_millis());
+    Ok(())
+}
+
+/// 从 server.properties 读取本机服务端的地址
+pub(crate) async fn local_address() -> (String, u16) {
+    let (host, port) = server_address().await;
+    (host.unwrap_or_else(|| "127.0.0.1".to_string()), port)
+}
+
+/// 服务端监听的地址，未指定 server-ip 时监听所有地址
+pub(crate) async fn listen_address() -> (String, u16) {
+    let (host, port) = server_address().await;
+    (host.unwrap_or_else(|| "0.0.0.0".to_string()), port)
+}
+
+async fn server_address() -> (Option<String>, u16) {
+    let props = match tokio::fs::read_to_string("server.properties").await {
+        Ok(s) => properties::parse(&s),
+        Err(_) => Vec::new(),
+    };
+    let port = properties::get(&props, "server-port")
+        .and_then(|p| p.trim().parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    let host = properties::get(&props, "server-ip")
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string);
+    (host, port)
+}
+
+/// 解析 host[:port]，IPv6 地址需使用 [addr]:port 的形式
+fn parse_address(address: &str) -> Result<(String, u16)> {
+    let invalid = || anyhow!("Invalid address: {address}");
+    if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+        let port = match rest.strip_prefix(':') {
+            Some(p) => p.parse().map_err(|_| invalid())?,
+            None if rest.is_empty() => DEFAULT_PORT,
+            None => return Err(invalid()),
+        };
+        return Ok((host.to_string(), port));
+    }
+    match address.split_once(':') {
+        Some((host, port)) => Ok((host.to_string(), port.parse().map_err(|_| invalid())?)),
+        None => Ok((address.to_string(), DEFAULT_PORT)),
+    }
+}
@@ -0,0 +1,228 @@
+use crate::core::config::project::McServerConfig;
+use crate::core::mc_server::McType;
+use crate::util::downloader::Downloader;
+use crate::util::lock::PidLock;
+use crate::util::properties;
+use anyhow::{Context, Result, anyhow};
+use md5::{Digest, Md5};
+use serde_json::{Value, json};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// 玩家名单的类型
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlayerList {
+    Whitelist,
+    Op,
+}
+
+/// 服务端对应的名单格式
+enum Format {
+    /// whitelist.json / ops.json，以 UUID 标识玩家
+    Java { online_mode: bool },
+    /// allowlist.json，以名称标识玩家
+    Bedrock,
+}
+
+impl PlayerList {
+    fn file(self, format: &Format) -> Result<&'static str> {
+        match (self, format) {
+            (PlayerList::Whitelist, Format::Java { .. }) => Ok("whitelist.json"),
+            (PlayerList::Op, Format::Java { .. }) => Ok("ops.json"),
+            (PlayerList::Whitelist, Format::Bedrock) => Ok("allowlist.json"),
+            // permissions.json 需要 XUID，无法通过名称查询
+            (PlayerList::Op, Format::Bedrock) => Err(anyhow!(
+                "Bedrock operators are identified by XUID, please use the op command in the server console"
+            )),
+        }
+    }
+    fn command(self) -> &'static str {
+        match self {
+            PlayerList::Whitelist => "whitelist",
+            PlayerList::Op => "op",
+        }
+    }
+}
+
+/// 添加玩家
+pub async fn add(list: PlayerList, names: Vec<String>) -> Result<()> {
+    let _lock = lock(list)?;
+    let props = read_properties().await;
+    let format = format(&props).await;
+    let file = list.file(&format)?;
+    let mut entries = read_list(file).await?;
+    for name in names {
+        if entries.iter().any(|e| same_name(e, &name)) {
+            warn!("{name} is already in {file}");
+            continue;
+        }
+        let entry = match &format {
+            Format::Bedrock => json!({ "ignoresPlayerLimit": false, "name": name }),
+            Format::Java { online_mode } => {
+                let (uuid, name) = if *online_mode {
+                    lookup_uuid(&name).await?
+                } else {
+                    (offline_uuid(&name), name)
+                };
+                match list {
+                    PlayerList::Whitelist => json!({ "uuid": uuid, "name": name }),
+                    PlayerList::Op => {
+                        let level = properties::get(&props, "op-permission-level")
+                            .and_then(|l| l.trim().parse::<u8>().ok())
+                            .unwrap_or(4);
+                        json!({
+                            "uuid": uuid,
+                            "name": name,
+                            "level": level,
+                            "bypassesPlayerLimit": false,
+                        })
+                    }
+                }
+            }
+        };
+        info!(
+            "Added {} to {file}",
+            entry["name"].as_str().unwrap_or_default()
+        );
+        entries.push(entry);
+    }
+    write_list(file, &entries).await
+}
+
+/// 移除玩家
+pub async fn remove(list: PlayerList, names: Vec<String>) -> Result<()> {
+    let _lock = lock(list)?;
+    let format = format(&read_properties().await).await;
+    let file = list.file(&format)?;
+    let mut entries = read_list(file).await?;
+    for name in names {
+        let before = entries.len();
+        entries.retain(|e| !same_name(e, &name));
+        if entries.len() == before {
+            warn!("{name} is not in {file}");
+        } else {
+            info!("Removed {name} from {file}");
+        }
+    }
+    write_list(file, &entries).await
+}
+
+/// 列出玩家
+pub async fn list(list: PlayerList) -> Result<()> {
+    let format = format(&read_properties().await).await;
+    let file = list.file(&format)?;
+    for entry in read_list(file).await? {
+        let name = entry["name"].as_str().unwrap_or_default();
+        match entry["uuid"].as_str() {
+            Some(uuid) => println!("{name}\t{uuid}"),
+            None => println!("{name}"),
+        }
+    }
+    Ok(())
+}
+
+/// 服务端运行时会覆盖对文件的修改
+fn lock(list: PlayerList) -> Result<PidLock> {
+    PidLock::acquire(&Path::new(".toymine").join("run.lock")).with_context(|| {
+        format!(
+            "The server is running, use the \"{}\" command in its console instead",
+            list.command()
+        )
+    })
+}
+
+async fn read_properties() -> Vec<(String, String)> {
+    match tokio::fs::read_to_string("server.properties").await {
+        Ok(s) => properties::parse(&s),
+        Err(_) => Vec::new(),
+    }
+}
+
+async fn format(props: &[(String, String)]) -> Format {
+    if let Some(cfg) = McServerConfig::current().await
+        && let McType::Bedrock(_) = cfg.project.version.server_type
+    {
+        return Format::Bedrock;
+    }
+    Format::Java {
+        online_mode: properties::get(props, "online-mode").is_none_or(|v| v.trim() != "false"),
+    }
+}
+
+async fn read_list(file: &str) -> Result<Vec<Value>> {
+    match tokio::fs::read_to_string(file).await {
+        Ok(s) if s.trim().is_empty() => Ok(Vec::new()),
+        Ok(s) => serde_json::from_str(&s).with_context(|| format!("Failed to parse {file}")),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn write_list(file: &str, entries: &[Value]) -> Result<()> {
+    tokio::fs::write(file, serde_json::to_string_pretty(entries)?).await?;
+    Ok(())
+}
+
+/// 玩家名称不区分大小写
+fn same_name(entry: &Value, name: &str) -> bool {
+    entry["name"]
+        .as_str()
+        .is_some_and(|n| n.eq_ignore_ascii_case(name))
+}
+
+/// 通过 Mojang API 查询正版玩家的 UUID 和名称
+async fn lookup_uuid(name: &str) -> Result<(String, String)> {
+    #[derive(serde::Deserialize)]
+    struct Profile {
+        id: String,
+        name: String,
+    }
+    let resp = Downloader::new()
+        .await
+        .get(format!(
+            "https://api.mojang.com/users/profiles/minecraft/{name}"
+        ))
+        .await
+        .with_context(|| format!("Failed to look up {name}"))?;
+    match resp.status().code() {
+        200 => {}
+        204 | 404 => return Err(anyhow!("Player {name} does not exist")),
+        code => return Err(anyhow!("Failed to look up {name}: HTTP {code}")),
+    }
+    let profile: Profile = serde_json::from_str(&resp.text().await?)?;
+    let uuid = uuid::Uuid::parse_str(&profile.id)?;
+    Ok((uuid.hyphenated().to_string(), profile.name))
+}
+
+/// 离线模式的 UUID，与服务端的 UUID.nameUUIDFromBytes("OfflinePlayer:" + name) 一致
+fn offline_uuid(name: &str) -> String {
+    let hash: [u8; 16] = Md5::digest(format!("OfflinePlayer:{name}")).into();
+    uuid::Builder::from_md5_bytes(hash)
+        .into_uuid()
+        .hyphenated()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_uuid_matches_the_server() {
+        assert_eq!(
+            offline_uuid("Notch"),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+        assert_eq!(offline_uuid("jeb_"), "a762f560-4fce-3236-812a-b80efff0b62b");
+        // 离线 UUID 区分大小写
+        assert_ne!(offline_uuid("notch"), offline_uuid("Notch"));
+    }
+
+    #[test]
+    fn same_name_ignores_case() {
+        let entry = json!({"uuid": offline_uuid("Notch"), "name": "Notch"});
+        assert!(same_name(&entry, "NOTCH"));
+        assert!(!same_name(&entry, "jeb_"));
+        assert!(!same_name(&json!({"uuid": ""}), "Notch"));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::core::config::project::McServerConfig;
+use crate::core::core_history::CoreHistory;
+use crate::core::mc_server::McVersion;
+use crate::util::lock::PidLock;
+use crate::util::output;
+use crate::versions::VersionManager;
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+
+pub async fn rollback(version: Option<String>, list: bool) -> Result<()> {
+    let history = CoreHistory::new();
+    if list {
+        let entries = history.list().await?;
+        if output::is_json() {
+            output::data(serde_json::to_value(&entries)?);
+            return Ok(());
+        }
+        for entry in entries.iter().rev() {
+            println!(
+                "{}\t{}\t{}",
+                entry.version,
+                &entry.sha1[..12],
+                entry.date.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        return Ok(());
+    }
+
+    let mut cfg = McServerConfig::current()
+        .await
+        .ok_or(anyhow!("The configuration file was not found"))?;
+    let server = VersionManager::from_cfg(&cfg).ok_or(anyhow!("MC Server Not Found"))?;
+    let server_file = cfg.project.server_file.clone();
+
+    // 运行中的服务端不能替换核心
+    let _lock = PidLock::acquire(&Path::new(".toymine").join("run.lock"))?;
+
+    let entry = history.find(version.as_deref(), &server_file).await?;
+    // 先保存当前核心，回滚后仍可恢复
+    history
+        .record(&server_file, &cfg.project.version, cfg.project.core_history)
+        .await?;
+    history.restore(&entry, &server_file).await?;
+
+    cfg.project.version = McVersion::from_str(&entry.version)?;
+    cfg.project.server_sha1 = Some(entry.sha1.clone());
+    cfg.save(server.as_ref()).await?;
+    info!("Rolled back to {}", entry.version);
+    output::data(json!({ "version": entry.version }));
+    Ok(())
+}
@@ -1,29 +1,59 @@
-use crate::command::CommandLoader;
-use crate::core::config::project::McServerConfig;
-use crate::core::mc_server::NotImplemented;
+use crate::command::event::{InstanceEvent, SERVER_EVENTS, ServerEvent};
+use crate::command::{CommandLoader, default_plugins};
+use crate::core::arguments::ping::local_address;
+use crate::core::config::project::{McServerConfig, ProjectCfg};
+use crate::core::crash_report;
+use crate::core::hibernate::{self, HibernateCfg};
+use crate::core::mc_server::base::McServer;
+use crate::core::mc_server::console;
 use crate::core::mc_server::runner::{Runner, sync_channel_stdio};
+use crate::core::mc_server::{McType, NotImplemented};
+use crate::util::hash::file_sha1;
+use crate::util::lock::PidLock;
+use crate::util::output;
 use crate::versions::VersionManager;
-use crate::{TASK_MANAGER, command};
+use crate::{BACKUP_MANAGER, GLOBAL_CACHE, TASK_MANAGER, command};
 use anyhow::Result;
 use anyhow::anyhow;
+use serde_json::json;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
 use tokio::select;
 use tokio::signal::ctrl_c;
-use tracing::info;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+/// 每个进程只运行一个项目，重启后服务端的 id 会变化，事件和备份计划使用固定的实例 id
+const INSTANCE: usize = 0;
 
 pub async fn start(generate: bool, _detach: bool, _attach: bool) -> Result<()> {
     // 尝试从当前目录获取配置文件
-    let cfg = McServerConfig::current().await;
+    let mut cfg = McServerConfig::current().await;
     // 尝试从当前目录发现服务端
-    let server = match cfg {
+    let server = match &cfg {
         None => {
             info!("The configuration file was not found. Attempting to locate the server file.");
             VersionManager::detect_server()?
         }
-        Some(c) => VersionManager::from_cfg(&c),
+        Some(c) => VersionManager::from_cfg(c),
+    };
+    // 命令插件，每次启动服务端时重新创建，此处提前检查配置
+    let plugin_cfg = match &cfg {
+        None => default_plugins(),
+        Some(c) => c.command_plugin.clone(),
     };
+    command::build_plugins(INSTANCE, &plugin_cfg)?;
+    // 停止的等待时间
+    let stop_timeout = match &cfg {
+        None => ProjectCfg::default().stop_timeout(),
+        Some(c) => c.project.stop_timeout(),
+    };
+    // IO 通道
+    let channels = cfg.as_ref().map(|c| c.channel).unwrap_or_default();
     let server = match server {
         None => return Err(anyhow!("MC Server Not Found")),
         Some(v) => v,
@@ -38,9 +68,20 @@ pub async fn start(generate: bool, _detach: bool, _attach: bool) -> Result<()> {
         let mut file = tokio::fs::File::create(save_path).await?;
         file.write_all(s.as_ref()).await?;
         file.flush().await?;
+        output::data(json!({ "script": save_path }));
 
         return Ok(());
     }
+    // 防止同一项目被重复启动
+    let _lock = PidLock::acquire(&Path::new(".toymine").join("run.lock"))?;
+
+    if let Some(cfg) = &mut cfg {
+        cfg.project.check_server_file()?;
+        if verify_core(&mut cfg.project, server.as_ref()).await? {
+            cfg.save(server.as_ref()).await?;
+        }
+    }
+
     match server.prepare().await {
         Ok(_) => {}
         Err(e) => {
@@ -49,31 +90,128 @@ pub async fn start(generate: bool, _detach: bool, _attach: bool) -> Result<()> {
             }
         }
     }
-    let server = Arc::new(Runner::spawn_server(server.as_ref()).await?);
-
-    let mut command_loader = CommandLoader::new();
-    command_loader.register(server.id, vec![Box::new(command::raw::ExamplePlugin)])?;
-    let server_clone = Arc::clone(&server);
-
-    TASK_MANAGER
-        .spawn_with_cancel(async move |t| {
-            sync_channel_stdio(
-                server_clone.input.clone(),
-                command_loader.load(server_clone.clone().as_ref()).await?,
-                t,
-            )
+    // Java 版启动完成后检查端口，基岩版使用 UDP 无法检查
+    let is_java = cfg
+        .as_ref()
+        .is_none_or(|c| matches!(c.project.version.server_type, McType::Java(_)));
+    if is_java && !plugin_cfg.iter().any(|p| p.name == "server_event") {
+        warn!("The port check requires the server_event command plugin, skipped");
+    } else if is_java {
+        let mut events = SERVER_EVENTS.subscribe();
+        TASK_MANAGER
+            .spawn_with_cancel(async move |t| {
+                loop {
+                    select! {
+                        event = events.recv() => match event {
+                            Ok(InstanceEvent { id: INSTANCE, event: ServerEvent::ServerDone }) => {
+                                check_port().await;
+                                break;
+                            }
+                            Ok(_) | Err(RecvError::Lagged(_)) => {}
+                            Err(RecvError::Closed) => break,
+                        },
+                        _ = t.cancelled() => break,
+                    }
+                }
+                Ok(())
+            })
             .await?;
-            Ok(())
-        })
-        .await?;
+    }
 
-    select! {
-        e = server.wait() => {
-            info!("Exit: {}",e?)
+    let restart = cfg.as_ref().map(|c| c.restart.clone()).unwrap_or_default();
+    // 基岩版使用 UDP，无法查询在线人数和监听连接
+    let hibernate = match &cfg {
+        Some(c) if is_java => c.hibernate.clone(),
+        Some(c) if c.hibernate.idle_minutes.is_some() => {
+            warn!("Hibernation is only supported by Java servers");
+            HibernateCfg::default()
+        }
+        _ => HibernateCfg::default(),
+    };
+    // 定时备份，整个运行期间只注册一次
+    if let Some(c) = &cfg
+        && c.backup.scheduled()
+    {
+        let mut backup = c.backup.clone();
+        // 玩家活动来自 server_event 插件，缺少时会跳过所有定时备份
+        if backup.skip_if_idle() && !plugin_cfg.iter().any(|p| p.name == "server_event") {
+            warn!("backup.option.skip_if_idle requires the server_event command plugin, ignored");
+            backup.set_skip_if_idle(false);
+        }
+        BACKUP_MANAGER
+            .register(backup, INSTANCE, &GLOBAL_CACHE)
+            .await?;
+        TASK_MANAGER
+            .spawn_with_cancel(async move |t| {
+                BACKUP_MANAGER.backup_thread(t).await;
+                Ok(())
+            })
+            .await?;
+    }
+    loop {
+        let started = SystemTime::now();
+        let runner = Arc::new(Runner::spawn_server(server.as_ref(), channels).await?);
+
+        let mut command_loader = CommandLoader::new();
+        command_loader.register(runner.id, command::build_plugins(INSTANCE, &plugin_cfg)?)?;
+        let runner_clone = Arc::clone(&runner);
+        // 重启时只停止本次的同步任务
+        let sync_token = TASK_MANAGER.cancel_token.child_token();
+        let sync_token_clone = sync_token.clone();
+
+        TASK_MANAGER
+            .spawn(async move || {
+                sync_channel_stdio(
+                    runner_clone.input.clone(),
+                    command_loader.load(runner_clone.clone().as_ref()).await?,
+                    sync_token_clone,
+                )
+                .await?;
+                Ok(())
+            })
+            .await?;
+
+        let next = select! {
+            e = runner.wait() => {
+                let status = e?;
+                info!("Exit: {}", status);
+                // 异常退出时提示崩溃报告
+                if !status.success() {
+                    crash_report::report(started).await;
+                }
+                Next::Exit
+            }
+            _ = shutdown_signal() => {
+                runner.kill_with_timeout(stop_timeout).await?;
+                info!("Stop: {}", runner.wait().await?);
+                Next::Exit
+            }
+            _ = restart.wait(&runner) => {
+                runner.kill_with_timeout(stop_timeout).await?;
+                info!("Stop: {}", runner.wait().await?);
+                Next::Restart
+            }
+            _ = hibernate.wait_idle() => {
+                runner.kill_with_timeout(stop_timeout).await?;
+                info!("Stop: {}", runner.wait().await?);
+                Next::Hibernate
+            }
+        };
+        sync_token.cancel();
+
+        if let Some(cfg) = &cfg
+            && cfg.backup.on_stop()
+            && let Err(e) = cfg.backup.snapshot(&GLOBAL_CACHE, "Stop").await
+        {
+            warn!("Failed to back up after stopping: {e:#}");
         }
-        _ = ctrl_c() => {
-            server.kill_with_timeout(std::time::Duration::from_secs(10)).await?;
-            info!("Stop: {}",server.wait().await?)
+        match next {
+            Next::Exit => break,
+            Next::Restart => info!("Restarting the server"),
+            Next::Hibernate => select! {
+                woken = hibernate::wait_connection() => woken?,
+                _ = shutdown_signal() => break,
+            },
         }
     }
 
@@ -81,3 +219,141 @@ pub async fn start(generate: bool, _detach: bool, _attach: bool) -> Result<()> {
 
     Ok(())
 }
+
+/// 服务端停止后的操作
+enum Next {
+    Exit,
+    Restart,
+    Hibernate,
+}
+
+/// 校验服务端文件与安装时记录的 SHA-1 是否一致，不一致时重新下载
+/// 返回是否重新下载，此时记录的摘要已更新，需要保存配置
+async fn verify_core(project: &mut ProjectCfg, server: &dyn McServer) -> Result<bool> {
+    let Some(expected) = &project.server_sha1 else {
+        return Ok(false);
+    };
+    let server_file = &project.server_file;
+    if let Ok(actual) = file_sha1(server_file).await
+        && actual.eq_ignore_ascii_case(expected)
+    {
+        return Ok(false);
+    }
+    warn!(
+        "{} does not match the recorded checksum, downloading {} again",
+        server_file.display(),
+        project.version.compact()
+    );
+    server
+        .install(project.version.clone())
+        .await
+        .map_err(|e| e.context("The server core is corrupted and could not be downloaded again"))?;
+    // 下载时已按来源提供的摘要校验，记录新的摘要
+    project.server_sha1 = Some(file_sha1(server_file).await?);
+    info!("The server core has been restored");
+    Ok(true)
+}
+
+/// 检查服务端端口是否在本机监听，仅作提示
+/// 依赖 server_event 插件提供的启动完成事件
+async fn check_port() {
+    let (ip, port) = local_address().await;
+    match timeout(
+        Duration::from_secs(3),
+        TcpStream::connect((ip.as_str(), port)),
+    )
+    .await
+    {
+        Ok(Ok(_)) => info!("The server is listening on {ip}:{port}"),
+        Ok(Err(e)) => warn!(
+            "Unable to connect to {ip}:{port}: {e}, check server-ip and server-port in server.properties"
+        ),
+        Err(_) => warn!("Timed out connecting to {ip}:{port}"),
+    }
+    // 本机可连接不代表外部可连接
+    info!(
+        "Players on other machines need TCP port {port} open in the firewall and forwarded by the router"
+    );
+}
+
+/// 等待退出信号（Ctrl-C 或 Unix 下的 SIGTERM）
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => select! {
+                _ = ctrl_c() => {}
+                _ = console::interrupted() => {}
+                _ = term.recv() => info!("Received SIGTERM"),
+            },
+            Err(_) => select! {
+                _ = ctrl_c() => {}
+                _ = console::interrupted() => {}
+            },
+        }
+    }
+    #[cfg(not(unix))]
+    select! {
+        _ = ctrl_c() => {}
+        _ = console::interrupted() => {}
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::core::mc_server::mock::{CORE, MockServer};
+    use crate::util::testing::temp_dir;
+    use std::sync::atomic::Ordering;
+
+    fn project(server_file: &Path, sha1: Option<String>) -> ProjectCfg {
+        ProjectCfg {
+            version: MockServer::version(),
+            server_file: server_file.to_path_buf(),
+            server_sha1: sha1,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn tampered_core_is_downloaded_again() {
+        let dir = temp_dir();
+        let jar = dir.join("server.jar");
+        tokio::fs::write(&jar, CORE).await.unwrap();
+        let sha1 = file_sha1(&jar).await.unwrap();
+        let server = MockServer::with_stop_delay(&jar, Duration::ZERO);
+
+        let mut cfg = project(&jar, Some(sha1.clone()));
+        assert!(!verify_core(&mut cfg, &server).await.unwrap());
+        assert_eq!(server.installs.load(Ordering::Relaxed), 0);
+
+        // 可以解析但内容有误的核心
+        tokio::fs::write(&jar, b"mock server corf").await.unwrap();
+        assert!(verify_core(&mut cfg, &server).await.unwrap());
+        assert_eq!(server.installs.load(Ordering::Relaxed), 1);
+        assert_eq!(tokio::fs::read(&jar).await.unwrap(), CORE);
+        assert_eq!(cfg.server_sha1, Some(sha1));
+
+        // 缺失的核心同样重新下载
+        tokio::fs::remove_file(&jar).await.unwrap();
+        assert!(verify_core(&mut cfg, &server).await.unwrap());
+        assert_eq!(server.installs.load(Ordering::Relaxed), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unrecorded_checksum_is_not_checked() {
+        let dir = temp_dir();
+        let jar = dir.join("server.jar");
+        tokio::fs::write(&jar, b"anything").await.unwrap();
+        let server = MockServer::with_stop_delay(&jar, Duration::ZERO);
+        assert!(
+            !verify_core(&mut project(&jar, None), &server)
+                .await
+                .unwrap()
+        );
+        assert_eq!(server.installs.load(Ordering::Relaxed), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use crate::GLOBAL_CACHE;
+use crate::command::event::{ServerEvent, ServerEventPlugin};
+use crate::core::config::project::McServerConfig;
+use crate::core::core_history::CoreHistory;
+use crate::core::mc_server::base::McServer;
+use crate::core::mc_server::runner::{ChannelsCfg, Runner};
+use crate::core::mc_server::{McChannel, McVersion};
+use crate::core::mc_server::{McType, NotImplemented};
+use crate::util::hash::{Checksum, file_digests, file_sha1};
+use crate::util::lock::PidLock;
+use crate::util::output;
+use crate::util::{nbt, properties};
+use crate::versions::VersionManager;
+use crate::versions::world_version;
+use anyhow::{Result, anyhow};
+use serde_json::json;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+pub async fn upgrade(
+    version: Option<String>,
+    allow_downgrade: bool,
+    accept_experimental: bool,
+    skip_smoke_test: bool,
+    smoke_timeout: u64,
+    verify: Option<String>,
+) -> Result<()> {
+    let verify = verify.map(|v| Checksum::from_str(&v)).transpose()?;
+    let mut cfg = McServerConfig::current()
+        .await
+        .ok_or(anyhow!("The configuration file was not found"))?;
+    let server = VersionManager::from_cfg(&cfg).ok_or(anyhow!("MC Server Not Found"))?;
+    let current = cfg.project.version.clone();
+
+    // 目标版本，未指定时使用最新版本
+    let target = match version {
+        None => server.latest().await?,
+        // 完整格式，形如 java:paper:1.21.1
+        Some(v) if v.contains(':') => McVersion::from_str(&v)?,
+        // 仅版本号，沿用当前的服务端类型
+        Some(v) => McVersion {
+            server_type: current.server_type.clone(),
+            channel: McChannel::from(v),
+        },
+    };
+    if target.server_type != current.server_type {
+        return Err(anyhow!(
+            "Switching the server type is not supported: {} -> {}",
+            current.compact(),
+            target.compact()
+        ));
+    }
+
+    // 快照可能破坏插件和世界，且世界无法回到正式版
+    if target.is_experimental() {
+        if !accept_experimental {
+            return Err(anyhow!(
+                "{} is an experimental version that may break plugins and convert the world to a format releases cannot load. Use --accept-experimental to continue",
+                target.compact()
+            ));
+        }
+        warn!(
+            "{} is an experimental version, back up the world before starting it",
+            target.compact()
+        );
+    }
+
+    match target.partial_cmp(&current) {
+        Some(Ordering::Equal) => {
+            info!("Already at {}", current.compact());
+            output::data(json!({ "version": current.compact(), "updated": false }));
+            return Ok(());
+        }
+        Some(Ordering::Less) if !allow_downgrade => {
+            return Err(anyhow!(
+                "{} is older than the current version {}, downgrading may corrupt the world. Use --allow-downgrade to continue",
+                target.compact(),
+                current.compact()
+            ));
+        }
+        Some(Ordering::Less) => warn!("Downgrading may corrupt the world"),
+        Some(Ordering::Greater) => {}
+        None => warn!(
+            "Unable to compare {} with {}, continuing",
+            target.compact(),
+            current.compact()
+        ),
+    }
+
+    // 运行中的服务端不能替换核心
+    let _lock = PidLock::acquire(&Path::new(".toymine").join("run.lock"))?;
+
+    if cfg.backup.on_update() {
+        info!("Creating a backup before updating");
+        cfg.backup
+            .snapshot(&GLOBAL_CACHE, &format!("Update {}", current.compact()))
+            .await?;
+    }
+
+    // 保存当前核心，用于回滚
+    let server_file = cfg.project.server_file.clone();
+    let history = CoreHistory::new();
+    history
+        .record(&server_file, &current, cfg.project.core_history)
+        .await?;
+
+    info!("Installing {}", target.compact());
+    server.install(target.clone()).await?;
+
+    // 校验用户提供的摘要
+    if let Some(verify) = &verify {
+        let digests = file_digests(&server_file).await?;
+        if let Err(e) = verify.check(&digests) {
+            restore_previous(&history, &server_file).await?;
+            return Err(e.context("The installed core failed verification"));
+        }
+        info!("Checksum verified");
+    }
+
+    if let McType::Java(_) = target.server_type
+        && let Err(e) = check_world_version(&server_file, allow_downgrade).await
+    {
+        restore_previous(&history, &server_file).await?;
+        return Err(e);
+    }
+
+    if skip_smoke_test {
+        info!("Smoke test skipped");
+    } else if let Err(e) = smoke_test(
+        server.as_ref(),
+        &target.server_type,
+        Duration::from_secs(smoke_timeout),
+    )
+    .await
+    {
+        restore_previous(&history, &server_file).await?;
+        return Err(e.context(format!("{} failed the smoke test", target.compact())));
+    }
+
+    cfg.project.version = target;
+    cfg.project.server_sha1 = Some(file_sha1(&server_file).await?);
+    cfg.save(server.as_ref()).await?;
+    info!(
+        "Updated from {} to {}",
+        current.compact(),
+        cfg.project.version.compact()
+    );
+    output::data(json!({
+        "version": cfg.project.version.compact(),
+        "previous": current.compact(),
+        "updated": true,
+    }));
+    Ok(())
+}
+
+/// 检查新核心能否打开当前的世界，数据版本低于世界时打开会损坏世界
+async fn check_world_version(server_file: &Path, allow_downgrade: bool) -> Result<()> {
+    let props = tokio::fs::read_to_string("server.properties")
+        .await
+        .map(|s| properties::parse(&s))
+        .unwrap_or_default();
+    let level = properties::level_name(&props);
+    let level_dat = Path::new(level).join("level.dat");
+    // 尚未生成世界
+    if tokio::fs::metadata(&level_dat).await.is_err() {
+        return Ok(());
+    }
+    let world = match nbt::read_level_dat(&level_dat).await {
+        Ok(info) => info.data_version,
+        Err(e) => {
+            warn!("Unable to read {}: {e}", level_dat.display());
+            return Ok(());
+        }
+    };
+    let target = world_version(server_file)?;
+    let (Some(world), Some(target)) = (world, target) else {
+        debug!("Data version unknown, world check skipped");
+        return Ok(());
+    };
+    if target >= world {
+        return Ok(());
+    }
+    let message = format!(
+        "The world \"{level}\" has data version {world}, but the new server core only supports {target}, opening it may corrupt the world"
+    );
+    if allow_downgrade {
+        warn!("{message}");
+        Ok(())
+    } else {
+        Err(anyhow!("{message}. Use --allow-downgrade to continue"))
+    }
+}
+
+/// 恢复安装前的核心
+async fn restore_previous(history: &CoreHistory, server_file: &Path) -> Result<()> {
+    if let Ok(entry) = history.find(None, server_file).await {
+        history.restore(&entry, server_file).await?;
+        warn!("Restored the previous server core {}", entry.version);
+    }
+    Ok(())
+}
+
+/// 启动新核心，等待启动完成后停止，失败时返回最后的输出
+async fn smoke_test(server: &dyn McServer, server_type: &McType, limit: Duration) -> Result<()> {
+    /// 失败时保留的输出行数
+    const TAIL: usize = 20;
+
+    // 未同意 EULA 的 Java 服务端会直接退出
+    if let McType::Java(_) = server_type {
+        let eula = tokio::fs::read_to_string("eula.txt")
+            .await
+            .unwrap_or_default();
+        if !eula.contains("eula=true") {
+            warn!("The EULA has not been accepted, smoke test skipped");
+            return Ok(());
+        }
+    }
+
+    if let Err(e) = server.prepare().await
+        && e.downcast_ref::<NotImplemented>().is_none()
+    {
+        return Err(e);
+    }
+
+    info!("Running smoke test, waiting up to {}s", limit.as_secs());
+    // 只用于解析，事件不会广播
+    let parser = ServerEventPlugin::new(0, &Default::default())?;
+    let runner = Runner::spawn_server(server, ChannelsCfg::default()).await?;
+    let deadline = Instant::now() + limit;
+    let mut tail = VecDeque::with_capacity(TAIL);
+    let mut exited = false;
+    let ready = loop {
+        tokio::select! {
+            // 只在每次接收时持有输出锁
+            line = async { runner.output.lock().await.recv().await } => match line {
+                None => break false,
+                Some(line) => {
+                    debug!("{}", line);
+                    if let Some(ServerEvent::ServerDone) = parser.parse(&line) {
+                        break true;
+                    }
+                    if tail.len() >= TAIL {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+            },
+            status = runner.wait() => {
+                tail.push_back(format!("Exited: {}", status?));
+                exited = true;
+                break false;
+            }
+            _ = tokio::time::sleep_until(deadline) => break false,
+        }
+    };
+
+    if !exited {
+        runner
+            .kill_with_timeout(Some(Duration::from_secs(30)))
+            .await?;
+        runner.wait().await?;
+    }
+
+    if ready {
+        info!("Smoke test passed");
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "The server did not become ready, last output:\n{}",
+            Vec::from(tail).join("\n")
+        ))
+    }
+}
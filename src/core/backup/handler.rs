@@ -1,57 +1,92 @@
 use anyhow::Result;
 use rustic_backend::BackendOptions;
 use rustic_core::{
-    BackupOptions, CheckOptions, ConfigOptions, CredentialOptions, IndexedFullStatus, KeyOptions,
-    LocalDestination, LsOptions, PathList, Repository, RepositoryOptions, RestoreOptions,
-    SnapshotOptions,
+    BackupOptions, CheckOptions, ConfigOptions, CredentialOptions, Credentials, IndexedFullStatus,
+    KeyOptions, LocalDestination, LsOptions, ParentOptions, PathList, Repository,
+    RepositoryOptions, RestoreOptions, SnapshotOptions,
 };
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use tracing::{debug, info};
 
 pub struct BackupRepo {
     source: Vec<PathBuf>,
     repo: Repository<IndexedFullStatus>,
+    skip_check: bool,
+}
+
+/// 仓库密码，读取 RUSTIC_PASSWORD 或 RUSTIC_PASSWORD_FILE，均未设置时使用空密码
+fn credentials() -> Result<Credentials> {
+    let mut opts = CredentialOptions::default();
+    if let Ok(password) = std::env::var("RUSTIC_PASSWORD") {
+        opts = opts.password(password);
+    } else if let Ok(file) = std::env::var("RUSTIC_PASSWORD_FILE") {
+        opts = opts.password_file(PathBuf::from(file));
+    }
+    Ok(opts.credentials()?.unwrap_or_else(|| {
+        debug!("No backup password configured, using an empty password");
+        Credentials::password("")
+    }))
 }
 
 impl BackupRepo {
-    pub fn init(path: &Path, cache: &Path, source: Vec<PathBuf>) -> Result<Self> {
+    pub fn init(backend: &BackendOptions, cache: &Path, source: Vec<PathBuf>) -> Result<Self> {
         debug!("backup_init_repo : Initialize backup repository");
 
         // Initialize Backends
-        let backends = BackendOptions::default()
-            .repository(path.to_string_lossy())
-            .to_backends()?;
+        let backends = backend.to_backends()?;
 
         // Init repository
         let repo_opts = RepositoryOptions::default().cache_dir(cache);
         let key_opts = KeyOptions::default();
-        let cred_opts = CredentialOptions::default()
-            .credentials()?
-            .expect("Credential Options Error");
+        let credentials = credentials()?;
         let config_opts = ConfigOptions::default();
-        let repo = Repository::new(&repo_opts, &backends)?
-            .init(&cred_opts, &key_opts, &config_opts)?
-            .to_indexed()?;
+        let repo = Repository::new(&repo_opts, &backends)?;
+        // 已初始化的仓库直接打开
+        let repo = if repo.config_id()?.is_some() {
+            repo.open(&credentials)?
+        } else {
+            repo.init(&credentials, &key_opts, &config_opts)?
+        }
+        .to_indexed()?;
 
-        Ok(BackupRepo { source, repo })
+        Ok(BackupRepo {
+            source,
+            repo,
+            skip_check: false,
+        })
+    }
+    /// 跳过备份前的仓库检查和源目录大小统计
+    /// 变化判断只看修改时间和大小，不比较 ctime 和 inode，未变化的文件引用上一快照的数据
+    pub fn skip_check(mut self, skip_check: bool) -> Self {
+        self.skip_check = skip_check;
+        self
     }
     fn check(&self) -> Result<&Self> {
         let opts = CheckOptions::default().trust_cache(false);
         self.repo.check(opts)?;
         Ok(self)
     }
-    pub fn snap(&self, tag: &str) -> Result<()> {
-        self.check()?;
-
-        let backup_opts = BackupOptions::default();
+    /// 创建快照，返回快照 ID
+    pub fn snap(&self, tag: &str) -> Result<String> {
+        let backup_opts = if self.skip_check {
+            BackupOptions::default().no_scan(true).parent_opts(
+                ParentOptions::default()
+                    .ignore_ctime(true)
+                    .ignore_inode(true),
+            )
+        } else {
+            self.check()?;
+            BackupOptions::default()
+        };
         let source = PathList::from_iter(self.source.iter()).sanitize()?;
         let snap = SnapshotOptions::default().add_tags(tag)?.to_snapshot()?;
 
         // Create snapshot
         let snap = self.repo.backup(&backup_opts, &source, snap)?;
 
-        println!("successfully created snapshot:\n{snap:#?}");
-        Ok(())
+        let id = snap.id.to_string();
+        info!("Snapshot {id} created ({tag})");
+        Ok(id)
     }
     pub fn restore(&self, snap: &str, destination: &str) -> Result<()> {
         self.check()?;
@@ -1,23 +1,32 @@
 mod handler;
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::thread::sleep;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use tokio::sync::Mutex;
 use tokio::task::spawn_blocking;
-use tokio::time::sleep_until;
 use tokio::time::{Duration, Instant};
+use tokio::time::{sleep, sleep_until};
 
 use anyhow::{Context, Result};
 use chrono::Utc;
 use cron::Schedule;
-use serde::{Deserialize, Serialize};
+use rustic_backend::util::location_to_type_and_path;
+use rustic_backend::{BackendOptions, SupportedBackend};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use tokio::select;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
 use tokio_util::sync::CancellationToken;
-use tracing::debug;
+use tracing::{debug, error, info};
 
+use crate::TASK_MANAGER;
+use crate::command::event::{InstanceEvent, SERVER_EVENTS, ServerEvent};
 use crate::core::backup::handler::BackupRepo;
+use crate::core::config::storage::StorageCfg;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BackupCfg {
@@ -35,14 +44,71 @@ struct BackupOption {
     on_update: bool,
     /// 运行期 cron 备份
     cron: Option<Schedule>,
+    /// 跳过备份前的仓库检查和源目录大小统计，适合大型世界的定时备份
+    /// 仍会遍历全部来源，只是变化判断忽略 ctime 和 inode
+    #[serde(default)]
+    skip_check: bool,
+    /// 自上次备份以来没有玩家在线时跳过定时备份，需要 server_event 命令插件
+    #[serde(default)]
+    skip_if_idle: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 struct BackupPath {
     /// 备份来源
     source: Vec<PathBuf>,
-    /// 备份位置
-    repository: PathBuf,
+    /// 备份位置，本地路径或 rustic 的后端地址
+    /// 例如 `rclone:remote:toymine`、`rest:https://host/repo`、`opendal:s3`
+    #[serde(deserialize_with = "repository")]
+    repository: String,
+    /// 后端选项，例如 opendal:s3 的 bucket、root、endpoint
+    /// `${NAME}` 形式的值从环境变量读取，避免将密钥写入配置文件
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    options: BTreeMap<String, String>,
+}
+
+/// 读取配置时检查后端类型
+fn repository<'de, D: Deserializer<'de>>(de: D) -> Result<String, D::Error> {
+    let repository = String::deserialize(de)?;
+    location_to_type_and_path(&repository).map_err(|_| {
+        D::Error::custom(format!(
+            "unsupported backup repository `{repository}`, \
+             expected a local path or a local:, rclone:, rest: or opendal: backend"
+        ))
+    })?;
+    Ok(repository)
+}
+
+impl BackupPath {
+    /// 仓库的后端配置，本地仓库的位置受 [storage] 配置影响
+    fn backend(&self) -> Result<BackendOptions> {
+        let (kind, location) = location_to_type_and_path(&self.repository)?;
+        let repository = match kind {
+            SupportedBackend::Local => StorageCfg::current()
+                .backup_repository(Path::new(location.as_str()))
+                .to_string_lossy()
+                .into_owned(),
+            _ => self.repository.clone(),
+        };
+        let options = self
+            .options
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), expand_env(v)?)))
+            .collect::<Result<BTreeMap<_, _>>>()?;
+        Ok(BackendOptions::default()
+            .repository(repository)
+            .options(options))
+    }
+}
+
+/// 展开 `${NAME}` 形式的选项值
+fn expand_env(value: &str) -> Result<String> {
+    match value.strip_prefix("${").and_then(|v| v.strip_suffix('}')) {
+        Some(name) => {
+            std::env::var(name).with_context(|| format!("Environment variable {name} is not set"))
+        }
+        None => Ok(value.to_string()),
+    }
 }
 
 impl Default for BackupCfg {
@@ -53,15 +119,58 @@ impl Default for BackupCfg {
                 on_stop: true,
                 on_update: false,
                 cron: None,
+                skip_check: false,
+                skip_if_idle: false,
             },
             path: BackupPath {
                 source: vec![PathBuf::from("world")],
-                repository: PathBuf::from(".toymine").join("backup"),
+                repository: ".toymine/backup".to_string(),
+                options: BTreeMap::new(),
             },
         }
     }
 }
 
+impl BackupCfg {
+    /// 设置备份来源
+    pub fn set_source(&mut self, source: Vec<PathBuf>) {
+        self.path.source = source;
+    }
+    /// 更新时是否备份
+    pub fn on_update(&self) -> bool {
+        self.option.on_update
+    }
+    /// 停止时是否备份
+    pub fn on_stop(&self) -> bool {
+        self.option.on_stop
+    }
+    /// 是否有定时备份
+    pub fn scheduled(&self) -> bool {
+        self.option.cron.is_some()
+    }
+    /// 无人时是否跳过定时备份
+    pub fn skip_if_idle(&self) -> bool {
+        self.option.skip_if_idle
+    }
+    pub fn set_skip_if_idle(&mut self, skip: bool) {
+        self.option.skip_if_idle = skip;
+    }
+    /// 立即创建一次快照，无需注册到备份管理器，返回快照 ID
+    pub async fn snapshot(&self, cache_dir: &Path, tag: &str) -> Result<String> {
+        let path = self.path.clone();
+        let backend = path.backend()?;
+        let cache_dir = cache_dir.to_path_buf();
+        let tag = tag.to_string();
+        let skip_check = self.option.skip_check;
+        spawn_blocking(move || {
+            BackupRepo::init(&backend, &cache_dir, path.source)?
+                .skip_check(skip_check)
+                .snap(&tag)
+        })
+        .await?
+    }
+}
+
 /// 多实例的备份管理器
 pub struct BackupManager {
     schedule: Mutex<VecDeque<BackupTask>>,
@@ -72,6 +181,61 @@ struct BackupTask {
     next: Instant,
     schedule: Schedule,
     repo: Arc<BackupRepo>,
+    /// 需要跳过无人时的备份时记录玩家活动
+    activity: Option<Arc<PlayerActivity>>,
+}
+
+/// 自上次备份以来的玩家活动
+#[derive(Default)]
+struct PlayerActivity {
+    online: AtomicUsize,
+    active: AtomicBool,
+}
+
+impl PlayerActivity {
+    /// 根据实例的服务端事件更新，直到实例被移除
+    async fn track(
+        self: Arc<Self>,
+        id: usize,
+        mut events: Receiver<InstanceEvent>,
+        t: CancellationToken,
+    ) {
+        loop {
+            let event = select! {
+                event = events.recv() => match event {
+                    Ok(e) if e.id == id => e.event,
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+                _ = t.cancelled() => return,
+            };
+            match event {
+                ServerEvent::PlayerJoined { name } => {
+                    let online = self.online.fetch_add(1, Ordering::Relaxed) + 1;
+                    self.active.store(true, Ordering::Relaxed);
+                    debug!("{name} joined, {online} players online");
+                }
+                ServerEvent::PlayerLeft { name } => {
+                    let _ = self
+                        .online
+                        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+                    debug!("{name} left");
+                }
+                // 服务端重新启动时没有玩家在线
+                ServerEvent::ServerDone => self.online.store(0, Ordering::Relaxed),
+                _ => {}
+            }
+        }
+    }
+    /// 是否需要备份，并开始新的统计周期
+    fn take(&self) -> bool {
+        let online = self.online.load(Ordering::Relaxed) > 0;
+        self.active.swap(online, Ordering::Relaxed) || online
+    }
+    /// 备份失败，恢复 take 清除的活动标记，下次定时备份时不会跳过
+    fn restore(&self) {
+        self.active.store(true, Ordering::Relaxed);
+    }
 }
 
 impl BackupManager {
@@ -80,22 +244,40 @@ impl BackupManager {
             schedule: Mutex::new(VecDeque::new()),
         }
     }
-    pub async fn register(&self, cfg: BackupCfg, id: usize, cache_dir: &Path) {
+    pub async fn register(&self, cfg: BackupCfg, id: usize, cache_dir: &Path) -> Result<()> {
         let repo = Arc::new(
-            BackupRepo::init(&cfg.path.repository, &cache_dir, cfg.path.source.clone())
-                .expect("Failed to init backup repo"),
+            BackupRepo::init(&cfg.path.backend()?, &cache_dir, cfg.path.source.clone())
+                .context("Failed to init backup repo")?
+                .skip_check(cfg.option.skip_check),
         );
 
         if let Some(s) = &cfg.option.cron {
+            let activity = if cfg.option.skip_if_idle {
+                let activity = Arc::new(PlayerActivity::default());
+                let tracker = Arc::clone(&activity);
+                // 先订阅，避免遗漏任务启动前的事件
+                let events = SERVER_EVENTS.subscribe();
+                TASK_MANAGER
+                    .spawn_with_cancel(async move |t| {
+                        tracker.track(id, events, t).await;
+                        Ok(())
+                    })
+                    .await?;
+                Some(activity)
+            } else {
+                None
+            };
             let task = BackupTask {
                 id,
                 next: BackupManager::next_time(s),
                 schedule: s.clone(),
                 repo,
+                activity,
             };
             self.schedule.lock().await.push_back(task);
             debug!("Backup plan has been registered.")
         }
+        Ok(())
     }
     pub async fn remove(&self, id: usize) {
         self.schedule.lock().await.retain(|x| x.id != id)
@@ -119,22 +301,38 @@ impl BackupManager {
             match self.schedule.lock().await.pop_front() {
                 None => {
                     // 无计划状态
-                    sleep(Duration::from_secs(1));
+                    sleep(Duration::from_secs(1)).await;
                     continue;
                 }
-                Some(t) => {
-                    if t.next > Instant::now() {
-                        sleep_until(t.next).await;
+                Some(task) => {
+                    if task.next > Instant::now() {
+                        select! {
+                            _ = sleep_until(task.next) => {}
+                            _ = t.cancelled() => return,
+                        }
                     }
 
-                    // 完成一次备份
-                    let repo = Arc::clone(&t.repo);
-                    let _ = spawn_blocking(move || repo.snap("Cron Schedule"));
+                    // 完成一次备份，自上次备份以来无人在线时跳过
+                    if task.activity.as_ref().is_none_or(|a| a.take()) {
+                        let repo = Arc::clone(&task.repo);
+                        let result = spawn_blocking(move || repo.snap("Cron Schedule"))
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|r| r);
+                        if let Err(e) = result {
+                            error!("Scheduled backup failed: {e:#}");
+                            if let Some(activity) = &task.activity {
+                                activity.restore();
+                            }
+                        }
+                    } else {
+                        info!("No players since the last backup, scheduled backup skipped");
+                    }
 
                     // 计划下一次备份
                     let task = BackupTask {
-                        next: BackupManager::next_time(&t.schedule),
-                        ..t
+                        next: BackupManager::next_time(&task.schedule),
+                        ..task
                     };
                     self.schedule.lock().await.push_back(task);
                 }
@@ -153,3 +351,67 @@ impl BackupManager {
         Instant::now() + dur_std
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: usize, event: ServerEvent) -> InstanceEvent {
+        InstanceEvent { id, event }
+    }
+
+    fn joined(id: usize) -> InstanceEvent {
+        event(
+            id,
+            ServerEvent::PlayerJoined {
+                name: "Steve".to_string(),
+            },
+        )
+    }
+
+    /// 发送事件，关闭通道后等待统计完成
+    async fn track(activity: &Arc<PlayerActivity>, events: Vec<InstanceEvent>) {
+        let (tx, rx) = tokio::sync::broadcast::channel(16);
+        let tracker = tokio::spawn(Arc::clone(activity).track(7, rx, CancellationToken::new()));
+        for e in events {
+            tx.send(e).unwrap();
+        }
+        drop(tx);
+        tracker.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn activity_tracks_only_its_instance() {
+        let activity = Arc::new(PlayerActivity::default());
+        // 其他实例的玩家不算作活动
+        track(
+            &activity,
+            vec![joined(8), event(7, ServerEvent::ServerDone)],
+        )
+        .await;
+        assert!(!activity.take());
+
+        let left = ServerEvent::PlayerLeft {
+            name: "Steve".to_string(),
+        };
+        track(&activity, vec![joined(7), event(7, left)]).await;
+        // 玩家已离开，但自上次备份以来有过活动
+        assert!(activity.take());
+        assert!(!activity.take());
+
+        track(&activity, vec![joined(7)]).await;
+        // 仍有玩家在线时每次都需要备份
+        assert!(activity.take());
+        assert!(activity.take());
+    }
+
+    #[test]
+    fn failed_backup_keeps_the_activity() {
+        let activity = PlayerActivity::default();
+        activity.active.store(true, Ordering::Relaxed);
+        assert!(activity.take());
+        activity.restore();
+        assert!(activity.take());
+        assert!(!activity.take());
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+/// 读取全局配置时的错误
+static LOAD_ERROR: OnceLock<String> = OnceLock::new();
+
+/// 位于 ~/.toymine/config.toml 的全局配置
+#[derive(Serialize, Deserialize, Default)]
+pub struct GlobalConfig {
+    /// 下载配置
+    #[serde(default)]
+    pub download: DownloadCfg,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DownloadCfg {
+    /// 请求使用的 User-Agent
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+    /// 按主机附加的请求头，主机名同时匹配其子域名
+    /// 例如 `[download.headers."papermc.io"]` 下的 `Referer = "..."`
+    #[serde(default)]
+    pub headers: HashMap<String, HashMap<String, String>>,
+    /// 等待服务器响应的秒数，不限制下载内容所需的时间
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    /// 每个请求最多尝试的次数，包括首次请求
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: usize,
+    /// 首次重试前等待的毫秒数，之后每次翻倍并附加随机抖动
+    #[serde(default = "default_retry_delay")]
+    pub retry_delay_ms: u64,
+    /// 所有请求使用的代理，`--proxy` 参数优先，未设置时使用代理环境变量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// 版本清单缓存的有效秒数，过期后才重新请求
+    #[serde(default = "default_manifest_ttl")]
+    pub manifest_ttl_secs: u64,
+}
+
+fn default_user_agent() -> String {
+    format!("toymine/{}", env!("CARGO_PKG_VERSION"))
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_max_attempts() -> usize {
+    3
+}
+
+fn default_retry_delay() -> u64 {
+    500
+}
+
+fn default_manifest_ttl() -> u64 {
+    60 * 60
+}
+
+impl Default for DownloadCfg {
+    fn default() -> Self {
+        Self {
+            user_agent: default_user_agent(),
+            headers: HashMap::new(),
+            timeout_secs: default_timeout(),
+            max_attempts: default_max_attempts(),
+            retry_delay_ms: default_retry_delay(),
+            proxy: None,
+            manifest_ttl_secs: default_manifest_ttl(),
+        }
+    }
+}
+
+impl GlobalConfig {
+    pub fn path() -> PathBuf {
+        std::env::home_dir()
+            .unwrap()
+            .join(".toymine")
+            .join("config.toml")
+    }
+    /// 读取全局配置，文件不存在或有误时使用默认值
+    /// 配置在日志初始化之前读取，错误由 warn_if_invalid 稍后提示
+    pub fn load() -> Self {
+        let path = Self::path();
+        match std::fs::read_to_string(&path) {
+            Ok(s) => toml::from_str(&s).unwrap_or_else(|e| {
+                let _ = LOAD_ERROR.set(format!("Ignoring invalid {}: {e}", path.display()));
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+    /// 提示读取全局配置时的错误
+    pub fn warn_if_invalid() {
+        if let Some(e) = LOAD_ERROR.get() {
+            warn!("{e}");
+        }
+    }
+}
+
+impl DownloadCfg {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+    pub fn manifest_ttl(&self) -> Duration {
+        Duration::from_secs(self.manifest_ttl_secs)
+    }
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts.max(1)
+    }
+    /// 获取适用于该主机的请求头
+    pub fn headers_for(&self, host: &str) -> impl Iterator<Item = (&String, &String)> {
+        self.headers
+            .iter()
+            .filter(move |(h, _)| {
+                host == h.as_str()
+                    || host
+                        .strip_suffix(h.as_str())
+                        .is_some_and(|p| p.ends_with('.'))
+            })
+            .flat_map(|(_, headers)| headers.iter())
+    }
+}
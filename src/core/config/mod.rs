@@ -1 +1,3 @@
+pub mod global;
 pub mod project;
+pub mod storage;
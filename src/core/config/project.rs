@@ -1,15 +1,24 @@
+use crate::command::{CommandPluginCfg, default_plugins};
 use crate::core::backup::BackupCfg;
+use crate::core::config::storage::StorageCfg;
+use crate::core::core_history;
+use crate::core::hibernate::HibernateCfg;
 use crate::core::mc_server::McChannel::Snapshot;
 use crate::core::mc_server::McType::Java;
 use crate::core::mc_server::McVersion;
 use crate::core::mc_server::base::McServer;
-use anyhow::Result;
+use crate::core::mc_server::runner::ChannelsCfg;
+use crate::core::restart::RestartCfg;
+use crate::versions::VersionManager;
+use anyhow::{Result, anyhow};
 use erased_serde::Deserializer;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 use tokio::io::AsyncReadExt;
 use toml::Value;
+use tracing::warn;
 
 #[derive(Serialize, Deserialize)]
 pub struct McServerConfig {
@@ -22,6 +31,21 @@ pub struct McServerConfig {
     pub(crate) inner: Value,
     /// 备份配置
     pub backup: BackupCfg,
+    /// 命令插件
+    #[serde(default = "default_plugins")]
+    pub command_plugin: Vec<CommandPluginCfg>,
+    /// IO 通道配置
+    #[serde(default)]
+    pub channel: ChannelsCfg,
+    /// 定时重启
+    #[serde(default)]
+    pub restart: RestartCfg,
+    /// 无人时休眠
+    #[serde(default)]
+    pub hibernate: HibernateCfg,
+    /// 缓存、运行时和备份的存储位置
+    #[serde(default)]
+    pub storage: StorageCfg,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -36,6 +60,58 @@ pub struct ProjectCfg {
     pub version: McVersion,
     /// 服务端文件
     pub server_file: PathBuf,
+    /// 安装时服务端文件的 SHA-1，启动时用于发现损坏的核心
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_sha1: Option<String>,
+    /// 保留的历史核心数量
+    #[serde(default = "default_core_history")]
+    pub core_history: usize,
+    /// 停止时等待服务端保存并退出的秒数，超时后强制结束
+    #[serde(default = "default_stop_timeout")]
+    pub stop_timeout_secs: u64,
+    /// 停止时一直等待，不强制结束
+    #[serde(default)]
+    pub never_force_kill: bool,
+}
+
+fn default_core_history() -> usize {
+    core_history::DEFAULT_KEEP
+}
+
+fn default_stop_timeout() -> u64 {
+    10
+}
+
+impl ProjectCfg {
+    /// 检查服务端文件与服务端类型是否一致，Jar 类服务端为 jar，基岩版和 Pumpkin 为可执行文件
+    pub fn check_server_file(&self) -> Result<()> {
+        let is_jar = self
+            .server_file
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("jar"));
+        let expects_jar = VersionManager::expects_jar(&self.version.server_type);
+        match (expects_jar, is_jar) {
+            (true, false) => Err(anyhow!(
+                "{} is not a jar file, but the server type is {}",
+                self.server_file.display(),
+                self.version.compact()
+            )),
+            (false, true) => Err(anyhow!(
+                "{} is a jar file, but the server type is {}",
+                self.server_file.display(),
+                self.version.compact()
+            )),
+            _ => Ok(()),
+        }
+    }
+    /// 停止的等待时间，None 表示不强制结束
+    pub fn stop_timeout(&self) -> Option<Duration> {
+        if self.never_force_kill {
+            None
+        } else {
+            Some(Duration::from_secs(self.stop_timeout_secs))
+        }
+    }
 }
 
 impl Default for ProjectCfg {
@@ -49,6 +125,10 @@ impl Default for ProjectCfg {
                 channel: Snapshot("Null".to_string()),
             },
             server_file: PathBuf::from_str("server.jar").unwrap(),
+            server_sha1: None,
+            core_history: core_history::DEFAULT_KEEP,
+            stop_timeout_secs: default_stop_timeout(),
+            never_force_kill: false,
         }
     }
 }
@@ -65,14 +145,28 @@ impl McServerConfig {
     pub async fn current() -> Option<Self> {
         match Self::open(Path::new("ToyMine.toml")).await {
             Ok(v) => Some(v),
-            Err(_) => None,
+            Err(e) => {
+                // 文件存在但无法解析时说明原因
+                if !e
+                    .downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+                {
+                    warn!("Unable to load ToyMine.toml: {e}");
+                }
+                None
+            }
         }
     }
     pub fn new() -> Self {
         Self {
             project: Default::default(),
-            inner: Value::String("".to_string()),
+            inner: Value::Table(Default::default()),
             backup: Default::default(),
+            command_plugin: default_plugins(),
+            channel: Default::default(),
+            restart: Default::default(),
+            hibernate: Default::default(),
+            storage: Default::default(),
         }
     }
     pub async fn open(path: &Path) -> Result<Self> {
@@ -86,8 +180,19 @@ impl McServerConfig {
             project: self.project.clone(),
             inner: Value::try_from(inner.to_config()?)?,
             backup: self.backup.clone(),
+            command_plugin: self.command_plugin.clone(),
+            channel: self.channel,
+            restart: self.restart.clone(),
+            hibernate: self.hibernate.clone(),
+            storage: self.storage.clone(),
         })?)
     }
+    /// 保存到当前目录的配置文件
+    pub async fn save(&self, inner: &dyn McServer) -> Result<()> {
+        let content = self.to_string(inner)?;
+        tokio::fs::write("ToyMine.toml", content).await?;
+        Ok(())
+    }
     pub fn load_from_str(config: &str, inner: &mut dyn McServer) -> Result<Self> {
         let cfg = toml::from_str::<Self>(config)?;
         let version_cfg = toml::to_string(&cfg.inner)?;
@@ -96,3 +201,68 @@ impl McServerConfig {
         Ok(cfg)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::mc_server::McType::{self, Bedrock};
+
+    fn project(server_type: McType, server_file: &str) -> ProjectCfg {
+        let mut cfg = ProjectCfg::default();
+        cfg.version.server_type = server_type;
+        cfg.server_file = PathBuf::from(server_file);
+        cfg
+    }
+
+    #[test]
+    fn matching_server_files_pass() {
+        for (server_type, file) in [
+            (Java("vanilla".to_string()), "server.jar"),
+            (Java("paper".to_string()), "server.JAR"),
+            (Java("pumpkin".to_string()), "pumpkin"),
+            (Java("pumpkin".to_string()), "pumpkin.exe"),
+            (Bedrock("bds".to_string()), "bedrock_server"),
+            (Bedrock("bds".to_string()), "bedrock_server.exe"),
+        ] {
+            assert!(
+                project(server_type, file).check_server_file().is_ok(),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn mismatched_server_files_fail() {
+        for (server_type, file) in [
+            (Java("vanilla".to_string()), "bedrock_server"),
+            (Java("paper".to_string()), "pumpkin.exe"),
+            (Java("pumpkin".to_string()), "server.jar"),
+            (Bedrock("bds".to_string()), "server.jar"),
+        ] {
+            assert!(
+                project(server_type, file).check_server_file().is_err(),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn stop_timeout() {
+        let mut cfg = ProjectCfg::default();
+        assert_eq!(cfg.stop_timeout(), Some(Duration::from_secs(10)));
+        cfg.stop_timeout_secs = 120;
+        assert_eq!(cfg.stop_timeout(), Some(Duration::from_secs(120)));
+        cfg.never_force_kill = true;
+        assert_eq!(cfg.stop_timeout(), None);
+
+        // 旧配置缺少这两项时使用默认值
+        let toml = toml::to_string(&ProjectCfg::default()).unwrap();
+        let toml: String = toml
+            .lines()
+            .filter(|l| !l.starts_with("stop_timeout_secs") && !l.starts_with("never_force_kill"))
+            .map(|l| format!("{l}\n"))
+            .collect();
+        let cfg: ProjectCfg = toml::from_str(&toml).unwrap();
+        assert_eq!(cfg.stop_timeout(), Some(Duration::from_secs(10)));
+    }
+}
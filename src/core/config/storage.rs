@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tracing::warn;
+
+/// 当前项目的存储位置，启动后首次使用时读取
+static STORAGE: LazyLock<StorageCfg> = LazyLock::new(StorageCfg::load);
+
+/// 项目配置中的 [storage]，用于将占用空间较大的数据放到其他磁盘
+/// 仅接受绝对路径，未设置时使用默认位置
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct StorageCfg {
+    /// 下载缓存目录，默认为 ~/.toymine/cache
+    pub cache: Option<PathBuf>,
+    /// 共享运行时目录，默认为 ~/.toymine/runtime
+    pub runtime: Option<PathBuf>,
+    /// 相对路径的备份仓库所在的目录，默认为项目目录
+    pub backup: Option<PathBuf>,
+}
+
+impl StorageCfg {
+    pub fn current() -> &'static Self {
+        &STORAGE
+    }
+    /// 从当前目录的 ToyMine.toml 读取，忽略其余配置
+    fn load() -> Self {
+        #[derive(Deserialize)]
+        struct Partial {
+            #[serde(default)]
+            storage: StorageCfg,
+        }
+        let Ok(s) = std::fs::read_to_string("ToyMine.toml") else {
+            return Self::default();
+        };
+        let storage = match toml::from_str::<Partial>(&s) {
+            Ok(p) => p.storage,
+            Err(_) => return Self::default(),
+        };
+        let absolute = |name: &str, path: Option<PathBuf>| {
+            path.filter(|p| {
+                let ok = p.is_absolute();
+                if !ok {
+                    warn!(
+                        "Ignoring storage.{name}: {} is not an absolute path",
+                        p.display()
+                    );
+                }
+                ok
+            })
+        };
+        Self {
+            cache: absolute("cache", storage.cache),
+            runtime: absolute("runtime", storage.runtime),
+            backup: absolute("backup", storage.backup),
+        }
+    }
+    pub fn cache_root(&self) -> PathBuf {
+        self.cache
+            .clone()
+            .unwrap_or_else(|| toymine_home().join("cache"))
+    }
+    pub fn runtime_root(&self) -> PathBuf {
+        self.runtime
+            .clone()
+            .unwrap_or_else(|| toymine_home().join("runtime"))
+    }
+    /// 备份仓库的实际位置
+    pub fn backup_repository(&self, repository: &Path) -> PathBuf {
+        match &self.backup {
+            Some(root) if repository.is_relative() => root.join(repository),
+            _ => repository.to_path_buf(),
+        }
+    }
+}
+
+fn toymine_home() -> PathBuf {
+    std::env::home_dir().unwrap().join(".toymine")
+}
@@ -0,0 +1,146 @@
+// 历史服务端核心，用于回滚
+
+use crate::core::mc_server::McVersion;
+use crate::util::hash::file_sha1;
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{debug, info};
+
+/// 默认保留的核心数量
+pub const DEFAULT_KEEP: usize = 5;
+
+/// 一个历史核心
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CoreEntry {
+    /// 版本，紧凑格式
+    pub version: String,
+    /// 文件 sha1
+    pub sha1: String,
+    /// 历史目录中的文件名
+    pub file: String,
+    /// 保存时间
+    pub date: chrono::DateTime<chrono::Local>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Manifest {
+    #[serde(default)]
+    core: Vec<CoreEntry>,
+}
+
+/// 位于 .toymine/cores 的核心历史
+pub struct CoreHistory {
+    dir: PathBuf,
+}
+
+impl CoreHistory {
+    pub fn new() -> Self {
+        Self {
+            dir: Path::new(".toymine").join("cores"),
+        }
+    }
+
+    async fn manifest(&self) -> Result<Manifest> {
+        match tokio::fs::read_to_string(self.dir.join("manifest.toml")).await {
+            Ok(s) => Ok(toml::from_str(&s)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Manifest::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, manifest: &Manifest) -> Result<()> {
+        tokio::fs::write(self.dir.join("manifest.toml"), toml::to_string(manifest)?).await?;
+        Ok(())
+    }
+
+    /// 所有历史核心，从旧到新
+    pub async fn list(&self) -> Result<Vec<CoreEntry>> {
+        Ok(self.manifest().await?.core)
+    }
+
+    /// 保存当前核心，并只保留最近 keep 个
+    pub async fn record(&self, server_file: &Path, version: &McVersion, keep: usize) -> Result<()> {
+        if !tokio::fs::try_exists(server_file).await? {
+            debug!("No server core to record");
+            return Ok(());
+        }
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let sha1 = file_sha1(server_file).await?;
+        let mut manifest = self.manifest().await?;
+
+        // 相同的文件只保存一份，移到最新
+        if let Some(pos) = manifest.core.iter().position(|c| c.sha1 == sha1) {
+            let mut entry = manifest.core.remove(pos);
+            entry.date = chrono::Local::now();
+            manifest.core.push(entry);
+        } else {
+            let version = version.compact();
+            let extension = server_file
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            // 版本号中的 ':' 不能用于 Windows 文件名
+            let file = format!("{}-{}{}", version.replace(':', "-"), &sha1[..12], extension);
+            tokio::fs::copy(server_file, self.dir.join(&file)).await?;
+            info!("Saved the current core as {}", file);
+            manifest.core.push(CoreEntry {
+                version,
+                sha1,
+                file,
+                date: chrono::Local::now(),
+            });
+        }
+
+        // 清理超出数量的旧核心
+        let excess = manifest.core.len().saturating_sub(keep.max(1));
+        for entry in manifest.core.drain(..excess) {
+            debug!("Pruning core {}", entry.file);
+            let _ = tokio::fs::remove_file(self.dir.join(&entry.file)).await;
+        }
+        self.save(&manifest).await
+    }
+
+    /// 查找历史核心，未指定版本时返回与当前核心不同的最新一个
+    pub async fn find(&self, version: Option<&str>, current: &Path) -> Result<CoreEntry> {
+        let manifest = self.manifest().await?;
+        let entry = match version {
+            Some(v) => manifest.core.iter().rev().find(|c| {
+                c.version == v
+                    || McVersion::from_str(&c.version)
+                        .map(|m| m.channel.to_string() == v)
+                        .unwrap_or(false)
+            }),
+            None => {
+                let sha1 = match tokio::fs::try_exists(current).await? {
+                    true => Some(file_sha1(current).await?),
+                    false => None,
+                };
+                manifest
+                    .core
+                    .iter()
+                    .rev()
+                    .find(|c| Some(&c.sha1) != sha1.as_ref())
+            }
+        };
+        entry.cloned().ok_or_else(|| match version {
+            Some(v) => anyhow!("No saved core for version {v}"),
+            None => anyhow!("No saved core to roll back to"),
+        })
+    }
+
+    /// 恢复历史核心到服务端文件
+    pub async fn restore(&self, entry: &CoreEntry, server_file: &Path) -> Result<()> {
+        let path = self.dir.join(&entry.file);
+        if file_sha1(&path)
+            .await
+            .with_context(|| format!("Saved core {} is missing", entry.file))?
+            != entry.sha1
+        {
+            return Err(anyhow!("Saved core {} is corrupted", entry.file));
+        }
+        tokio::fs::copy(&path, server_file).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+// 收集服务端崩溃后留下的报告
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{error, warn};
+
+/// 崩溃报告目录，JVM 的 hs_err 文件也移动到这里
+const CRASH_DIR: &str = "crash-reports";
+
+/// 崩溃报告
+pub struct CrashReport {
+    pub path: PathBuf,
+    /// 异常或信号所在的行
+    pub summary: Option<String>,
+}
+
+/// 查找启动后生成的崩溃报告，并将 hs_err_pid*.log 移动到 crash-reports
+pub async fn collect(since: SystemTime) -> Result<Vec<CrashReport>> {
+    tokio::task::spawn_blocking(move || collect_blocking(since)).await?
+}
+
+/// 输出崩溃报告的摘要
+pub async fn report(since: SystemTime) {
+    match collect(since).await {
+        Ok(reports) => {
+            for r in reports {
+                error!(
+                    "Crash report {}: {}",
+                    r.path.display(),
+                    r.summary.as_deref().unwrap_or("no summary found")
+                );
+            }
+        }
+        Err(e) => warn!("Failed to collect crash reports: {e}"),
+    }
+}
+
+fn collect_blocking(since: SystemTime) -> Result<Vec<CrashReport>> {
+    let mut reports = Vec::new();
+    let is_new = |path: &Path| {
+        std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|t| t >= since)
+    };
+
+    // JVM 致命错误日志写在工作目录
+    for path in read_dir(Path::new(".")) {
+        let is_hs_err = path.file_name().is_some_and(|n| {
+            let n = n.to_string_lossy();
+            n.starts_with("hs_err_pid") && n.ends_with(".log")
+        });
+        if !is_hs_err || !is_new(&path) {
+            continue;
+        }
+        let summary = summarize(&path, hs_err_summary);
+        // 移动失败时保留在原处
+        std::fs::create_dir_all(CRASH_DIR)?;
+        let target = Path::new(CRASH_DIR).join(path.file_name().unwrap_or_default());
+        let path = match std::fs::rename(&path, &target) {
+            Ok(_) => target,
+            Err(e) => {
+                warn!("Failed to move {}: {e}", path.display());
+                path
+            }
+        };
+        reports.push(CrashReport { path, summary });
+    }
+
+    // 游戏的崩溃报告
+    for path in read_dir(Path::new(CRASH_DIR)) {
+        if path.extension().is_some_and(|e| e == "txt") && is_new(&path) {
+            let summary = summarize(&path, crash_summary);
+            reports.push(CrashReport { path, summary });
+        }
+    }
+    Ok(reports)
+}
+
+fn read_dir(path: &Path) -> Vec<PathBuf> {
+    match std::fs::read_dir(path) {
+        Ok(rd) => rd.flatten().map(|e| e.path()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn summarize(path: &Path, f: fn(&str) -> Option<String>) -> Option<String> {
+    let content = std::fs::read(path).ok()?;
+    f(&String::from_utf8_lossy(&content))
+}
+
+/// 从 hs_err 日志中取出信号或错误原因
+/// 形如 "#  SIGSEGV (0xb) at pc=0x00007f..., pid=1234, tid=5678"
+fn hs_err_summary(content: &str) -> Option<String> {
+    const KEYS: &[&str] = &[
+        "SIGSEGV",
+        "SIGBUS",
+        "SIGILL",
+        "EXCEPTION_",
+        "insufficient memory",
+        "Internal Error",
+        "OutOfMemory",
+    ];
+    let header = content
+        .lines()
+        .take_while(|l| l.starts_with('#') || l.trim().is_empty());
+    header
+        .map(|l| l.trim_start_matches('#').trim())
+        .find(|l| KEYS.iter().any(|k| l.contains(k)))
+        .map(str::to_string)
+}
+
+/// 从游戏崩溃报告中取出描述和异常
+/// 形如 "Description: Ticking entity" 后空一行跟随 "java.lang.NullPointerException: ..."
+fn crash_summary(content: &str) -> Option<String> {
+    let mut lines = content.lines();
+    let description = lines
+        .by_ref()
+        .find_map(|l| l.strip_prefix("Description:"))?
+        .trim()
+        .to_string();
+    match lines.map(str::trim).find(|l| !l.is_empty()) {
+        Some(exception) => Some(format!("{description}: {exception}")),
+        None => Some(description),
+    }
+}
@@ -0,0 +1,62 @@
+// 无人时休眠，有连接时唤醒
+
+use crate::core::arguments::ping::{listen_address, local_address};
+use crate::util::slp;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::time::{Instant, sleep};
+use tracing::{debug, info};
+
+/// 检查在线人数的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 休眠配置，仅适用于 Java 版
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct HibernateCfg {
+    /// 连续无人在线多少分钟后停止服务端，未设置时不休眠
+    pub idle_minutes: Option<u64>,
+}
+
+impl HibernateCfg {
+    /// 等待服务端空闲达到设定时间，未启用时永不返回
+    pub async fn wait_idle(&self) {
+        let Some(minutes) = self.idle_minutes else {
+            return std::future::pending().await;
+        };
+        let limit = Duration::from_secs(minutes * 60);
+        let (host, port) = local_address().await;
+        let mut last_active = Instant::now();
+        loop {
+            sleep(POLL_INTERVAL).await;
+            // 查询失败时（例如仍在启动）不计入空闲时间
+            match slp::ping(&host, port, Duration::from_secs(5)).await {
+                Ok(status) if status.online == 0 => {
+                    if last_active.elapsed() >= limit {
+                        info!("No players for {minutes} minute(s), hibernating");
+                        return;
+                    }
+                }
+                Ok(_) => last_active = Instant::now(),
+                Err(e) => {
+                    debug!("Idle check failed: {e}");
+                    last_active = Instant::now();
+                }
+            }
+        }
+    }
+}
+
+/// 在服务端端口上等待连接，收到连接时返回
+/// 客户端的这次连接会被断开，重新连接时服务端已在启动
+pub async fn wait_connection() -> Result<()> {
+    let (host, port) = listen_address().await;
+    let listener = TcpListener::bind((host.as_str(), port))
+        .await
+        .with_context(|| format!("Failed to listen on {host}:{port}"))?;
+    info!("Hibernating, the server will start on the next connection to port {port}");
+    let (_, addr) = listener.accept().await?;
+    info!("Connection from {addr}, waking up the server");
+    Ok(())
+}
@@ -34,13 +34,13 @@ pub trait McServer: Any + Sync {
     /// 需要持久化的内部配置信息
     fn to_config(&self) -> Result<Box<dyn erased_serde::Serialize + '_>> {
         #[derive(Serialize)]
-        struct NoConfig;
-        Ok(Box::new(NoConfig))
+        struct NoConfig {}
+        Ok(Box::new(NoConfig {}))
     }
     /// 加载配置信息
     fn load_config(&mut self, de: &mut dyn erased_serde::Deserializer) -> Result<()> {
         #[derive(Deserialize)]
-        struct NoConfig;
+        struct NoConfig {}
         let _cfg = erased_serde::deserialize::<NoConfig>(de)?;
         Ok(())
     }
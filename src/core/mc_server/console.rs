@@ -0,0 +1,201 @@
+// 交互式控制台，提供行编辑、历史记录和命令补全
+// 仅在标准输入输出都是终端时使用，管道输入仍按行读取
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, ExternalPrinter, Helper};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::Notify;
+use tracing::{debug, warn};
+
+/// 历史记录保存在项目目录中
+const HISTORY_FILE: &str = ".toymine/console_history";
+
+/// 常用的服务端命令，用于补全第一个单词
+const COMMANDS: &[&str] = &[
+    "ban",
+    "ban-ip",
+    "banlist",
+    "clear",
+    "deop",
+    "difficulty",
+    "effect",
+    "gamemode",
+    "gamerule",
+    "give",
+    "help",
+    "kick",
+    "kill",
+    "list",
+    "op",
+    "pardon",
+    "pardon-ip",
+    "reload",
+    "save-all",
+    "save-off",
+    "save-on",
+    "say",
+    "seed",
+    "setworldspawn",
+    "spawnpoint",
+    "stop",
+    "summon",
+    "tell",
+    "tellraw",
+    "time",
+    "title",
+    "tp",
+    "weather",
+    "whitelist",
+    "xp",
+];
+
+/// 控制台收到的 Ctrl-C，终端处于原始模式时不会产生信号
+static INTERRUPTED: Notify = Notify::const_new();
+/// 在提示符上方输出，避免打乱正在编辑的行
+static PRINTER: OnceLock<Mutex<Box<dyn ExternalPrinter + Send>>> = OnceLock::new();
+/// 进入控制台前的终端设置，退出时恢复
+#[cfg(unix)]
+static TERMIOS: OnceLock<libc::termios> = OnceLock::new();
+
+/// 是否使用交互式控制台
+/// 读取线程在程序退出时仍处于原始模式，目前只能在 Unix 上恢复终端
+pub fn is_interactive() -> bool {
+    cfg!(unix) && std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// 在读取线程中循环读取输入，Ctrl-D 时返回
+pub fn read_lines(mut send: impl FnMut(String) -> bool) {
+    let mut editor = match Editor::<ConsoleHelper, FileHistory>::new() {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("Unable to start the interactive console: {e}");
+            return;
+        }
+    };
+    editor.set_helper(Some(ConsoleHelper));
+    #[cfg(unix)]
+    save_terminal();
+    match editor.create_external_printer() {
+        Ok(p) => {
+            let _ = PRINTER.set(Mutex::new(Box::new(p)));
+        }
+        Err(e) => debug!("External printer unavailable: {e}"),
+    }
+    let history = Path::new(HISTORY_FILE);
+    if history.exists()
+        && let Err(e) = editor.load_history(history)
+    {
+        warn!("Unable to load the console history: {e}");
+    }
+
+    loop {
+        match editor.readline("> ") {
+            Ok(line) => {
+                if !line.trim().is_empty() {
+                    let _ = editor.add_history_entry(line.as_str());
+                    if let Err(e) = editor.append_history(history) {
+                        debug!("Unable to save the console history: {e}");
+                    }
+                }
+                if !send(line) {
+                    return;
+                }
+            }
+            Err(ReadlineError::Interrupted) => INTERRUPTED.notify_one(),
+            Err(ReadlineError::Eof) => return,
+            Err(e) => {
+                warn!("Console error: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// 输出到提示符上方，未使用交互式控制台时原样返回
+pub fn print(text: String) -> Option<String> {
+    let Some(printer) = PRINTER.get() else {
+        return Some(text);
+    };
+    if let Ok(mut printer) = printer.lock()
+        && let Err(e) = printer.print(text)
+    {
+        debug!("Console print error: {e}");
+    }
+    None
+}
+
+/// 等待控制台中的 Ctrl-C
+pub async fn interrupted() {
+    INTERRUPTED.notified().await
+}
+
+#[cfg(unix)]
+fn save_terminal() {
+    let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+    // SAFETY: tcgetattr 成功时已初始化 termios
+    unsafe {
+        if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) == 0 {
+            let _ = TERMIOS.set(termios.assume_init());
+        }
+    }
+}
+
+/// 恢复终端设置，读取线程退出前终端仍处于原始模式
+pub fn restore_terminal() {
+    #[cfg(unix)]
+    if let Some(termios) = TERMIOS.get() {
+        // SAFETY: termios 来自 tcgetattr
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios);
+        }
+        // 同时关闭读取时开启的括号粘贴模式
+        print!("\x1b[?2004l");
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+}
+
+struct ConsoleHelper;
+
+impl Completer for ConsoleHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        // 只补全命令名
+        let word = &line[..pos];
+        if word.contains(' ') {
+            return Ok((pos, Vec::new()));
+        }
+        let word = word.strip_prefix('/').unwrap_or(word);
+        let candidates = COMMANDS
+            .iter()
+            .filter(|c| c.starts_with(word))
+            .map(|c| Pair {
+                display: c.to_string(),
+                replacement: c.to_string(),
+            })
+            .collect();
+        Ok((pos - word.len(), candidates))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ConsoleHelper {}
+
+impl Validator for ConsoleHelper {}
+
+impl Helper for ConsoleHelper {}
@@ -0,0 +1,110 @@
+// 测试用的模拟服务端
+
+use crate::core::mc_server::base::{McServer, McVersion};
+use crate::core::mc_server::runner::Runner;
+use crate::core::mc_server::update::McServerUpdate;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// 输出与原版相似的日志，回显收到的命令，收到 stop 后等待 STOP_DELAY 秒退出
+const SCRIPT: &str = r#"
+echo '[Server thread/INFO]: Starting minecraft server'
+echo '[Server thread/INFO]: Done (0.1s)! For help, type "help"'
+while IFS= read -r line; do
+    case "$line" in
+        stop)
+            echo '[Server thread/INFO]: Stopping server'
+            # exec 使结束服务端时 sleep 一同结束
+            exec sleep "$STOP_DELAY"
+            ;;
+        *) echo "> $line" ;;
+    esac
+done
+"#;
+
+/// 启动完成时输出的行
+pub const DONE: &str = r#"[Server thread/INFO]: Done (0.1s)! For help, type "help""#;
+
+/// install 写入服务端文件的内容
+pub const CORE: &[u8] = b"mock server core";
+
+/// 模拟服务端，通过 sh 运行脚本，不需要 JVM
+pub struct MockServer {
+    /// 服务端文件，install 时写入 CORE
+    pub server_file: PathBuf,
+    /// 收到 stop 后延迟退出的时间
+    pub stop_delay: Duration,
+    /// install 被调用的次数
+    pub installs: AtomicUsize,
+}
+
+impl MockServer {
+    pub fn with_stop_delay(path: &Path, stop_delay: Duration) -> Self {
+        Self {
+            server_file: path.to_path_buf(),
+            stop_delay,
+            installs: AtomicUsize::new(0),
+        }
+    }
+    /// 模拟服务端的版本
+    pub fn version() -> McVersion {
+        "java:vanilla:1.21.1".parse().unwrap()
+    }
+}
+
+impl McServer for MockServer {
+    fn new(path: &Path) -> Box<dyn McServer>
+    where
+        Self: Sized,
+    {
+        Box::new(Self::with_stop_delay(path, Duration::ZERO))
+    }
+
+    fn script(&self) -> Result<String> {
+        Ok(SCRIPT.to_string())
+    }
+
+    fn start(&self) -> Result<Command> {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(SCRIPT)
+            .env("STOP_DELAY", self.stop_delay.as_secs_f64().to_string());
+        Ok(command)
+    }
+
+    fn impl_update<'a>(&'a self) -> Option<&'a dyn McServerUpdate> {
+        Some(self)
+    }
+}
+
+#[async_trait]
+impl McServerUpdate for MockServer {
+    async fn latest_version(&self) -> Result<McVersion> {
+        Ok(Self::version())
+    }
+
+    async fn install_version(&self, _target: McVersion) -> Result<()> {
+        self.installs.fetch_add(1, Ordering::Relaxed);
+        tokio::fs::write(&self.server_file, CORE).await?;
+        Ok(())
+    }
+}
+
+/// 读取输出直到出现包含 pattern 的行，返回读到的所有行
+pub async fn read_until(runner: &Runner, pattern: &str) -> Vec<String> {
+    let mut output = runner.output.lock().await;
+    let mut lines = Vec::new();
+    while let Some(line) = output.recv().await {
+        let found = line.contains(pattern);
+        lines.push(line);
+        if found {
+            break;
+        }
+    }
+    lines
+}
@@ -1,15 +1,20 @@
 pub mod base;
+pub mod console;
+#[cfg(all(test, unix))]
+pub mod mock;
 pub mod plugin;
 pub mod runner;
 pub mod runtime;
 pub mod update;
 
+use anyhow::anyhow;
 use colored::Colorize;
 use erased_serde::__private::serde::{Deserializer, Serializer};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::cmp::Ordering::{Equal, Greater, Less};
 use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
 
 pub enum NotImplemented {
     Update,
@@ -141,13 +146,7 @@ impl Serialize for McChannel {
     where
         S: Serializer,
     {
-        match self {
-            McChannel::Release(major, minor, patch) => {
-                serializer.serialize_str(format!("{}.{}.{}", major, minor, patch).as_str())
-            }
-            McChannel::Snapshot(s) => serializer.serialize_str(s),
-            McChannel::Unknown => serializer.serialize_str("Unknown"),
-        }
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -157,7 +156,12 @@ impl<'de> Deserialize<'de> for McChannel {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
+        Ok(Self::from(s))
+    }
+}
 
+impl From<String> for McChannel {
+    fn from(s: String) -> Self {
         let parts = s
             .split('.')
             .map(|x| x.parse::<u8>())
@@ -166,18 +170,137 @@ impl<'de> Deserialize<'de> for McChannel {
         match parts {
             Ok(v) => {
                 if v.len() == 3 {
-                    Ok(Self::Release(v[0], v[1], v[2]))
+                    Self::Release(v[0], v[1], v[2])
                 } else {
-                    Ok(Self::Snapshot(s))
+                    Self::Snapshot(s)
                 }
             }
             Err(_) => {
                 if s.trim() == "Unknown" {
-                    Ok(McChannel::Unknown)
+                    McChannel::Unknown
                 } else {
-                    Ok(Self::Snapshot(s))
+                    Self::Snapshot(s)
                 }
             }
         }
     }
 }
+
+impl Display for McChannel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            McChannel::Release(major, minor, patch) => write!(f, "{}.{}.{}", major, minor, patch),
+            McChannel::Snapshot(s) => f.write_str(s),
+            McChannel::Unknown => f.write_str("Unknown"),
+        }
+    }
+}
+
+impl McVersion {
+    /// 紧凑格式，形如 `java:paper:1.21.1` `bedrock:bds:1.20.70.21`
+    /// 与 FromStr 互为逆操作
+    pub fn compact(&self) -> String {
+        match &self.server_type {
+            McType::Java(t) => format!("java:{}:{}", t, self.channel),
+            McType::Bedrock(t) => format!("bedrock:{}:{}", t, self.channel),
+        }
+    }
+    /// 是否为 Java 版的快照、预发布或候选版本，例如 24w14a 1.21-pre1 1.21-rc1
+    /// 基岩版的预览版与正式版格式相同，无法区分
+    pub fn is_experimental(&self) -> bool {
+        let (McType::Java(_), McChannel::Snapshot(v)) = (&self.server_type, &self.channel) else {
+            return false;
+        };
+        let v = v.to_lowercase();
+        let b = v.as_bytes();
+        let weekly = b.len() == 6
+            && b[..2].iter().all(u8::is_ascii_digit)
+            && b[2] == b'w'
+            && b[3..5].iter().all(u8::is_ascii_digit);
+        weekly
+            || v.contains("-pre")
+            || v.contains("-rc")
+            || v.contains("pre-release")
+            || v.contains("snapshot")
+    }
+}
+
+/// 从紧凑格式解析版本信息
+impl FromStr for McVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, ':');
+        let (Some(edition), Some(name), Some(version)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow!(
+                "Invalid version \"{s}\", expected <edition>:<type>:<version>, e.g. java:paper:1.21.1"
+            ));
+        };
+        if name.is_empty() {
+            return Err(anyhow!("Invalid version \"{s}\": server type is empty"));
+        }
+        if version.is_empty() {
+            return Err(anyhow!("Invalid version \"{s}\": version is empty"));
+        }
+        let server_type = match edition.to_lowercase().as_str() {
+            "java" => McType::Java(name.to_string()),
+            "bedrock" => McType::Bedrock(name.to_string()),
+            _ => {
+                return Err(anyhow!(
+                    "Invalid version \"{s}\": unknown edition \"{edition}\", expected java or bedrock"
+                ));
+            }
+        };
+        Ok(McVersion {
+            server_type,
+            channel: McChannel::from(version.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_round_trip() {
+        for s in [
+            "java:vanilla:1.21.1",
+            "java:paper:24w14a",
+            "java:leaves:1.21-pre1",
+            "bedrock:bds:1.20.70.21",
+            "java:vanilla:Unknown",
+        ] {
+            assert_eq!(s.parse::<McVersion>().unwrap().compact(), s);
+        }
+    }
+
+    #[test]
+    fn from_str_parses_parts() {
+        let v: McVersion = " Java:paper:1.21.1 ".parse().unwrap();
+        assert!(v.server_type == McType::Java("paper".to_string()));
+        assert!(v.channel == McChannel::Release(1, 21, 1));
+
+        let v: McVersion = "bedrock:bds:1.20.70.21".parse().unwrap();
+        assert!(v.server_type == McType::Bedrock("bds".to_string()));
+        assert!(v.channel == McChannel::Snapshot("1.20.70.21".to_string()));
+
+        let v: McVersion = "java:vanilla:Unknown".parse().unwrap();
+        assert!(v.channel == McChannel::Unknown);
+    }
+
+    #[test]
+    fn from_str_rejects_invalid() {
+        for s in [
+            "",
+            "1.21.1",
+            "java:1.21.1",
+            "java::1.21.1",
+            "java:paper:",
+            "pocket:bds:1.20.70",
+        ] {
+            assert!(s.parse::<McVersion>().is_err(), "{s}");
+        }
+    }
+}
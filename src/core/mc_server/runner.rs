@@ -1,34 +1,57 @@
 use std::ops::Add;
 use std::process::{ExitStatus, Stdio};
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
 use std::time::Duration;
 
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Child;
 use tokio::select;
 use tokio::sync::Mutex;
-use tokio::sync::mpsc::{Receiver, Sender, channel};
 use tokio::time::{sleep, timeout};
 
 use crate::TASK_MANAGER;
 use crate::core::mc_server::base::McServer;
+use crate::core::mc_server::console;
+use crate::util::channel::{
+    ChannelCfg, ChannelPolicy, PolicyReceiver, PolicySender, policy_channel,
+};
 use anyhow::{Context, Result, anyhow};
 use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, trace, warn};
 
+/// 服务端 IO 通道配置
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ChannelsCfg {
+    /// 命令 -> 服务端，默认不丢弃命令
+    pub input: ChannelCfg,
+    /// 服务端 -> 终端，默认丢弃旧输出以保证延迟
+    pub output: ChannelCfg,
+}
+
+impl Default for ChannelsCfg {
+    fn default() -> Self {
+        ChannelsCfg {
+            input: ChannelCfg::new(32, ChannelPolicy::Block),
+            output: ChannelCfg::new(32, ChannelPolicy::DropOldest),
+        }
+    }
+}
+
 pub struct Runner {
     pub id: usize,
-    pub input: Arc<Sender<String>>,
-    pub output: Arc<Mutex<Receiver<String>>>,
-    stop: Mutex<Option<tokio::sync::oneshot::Sender<Duration>>>,
+    pub input: Arc<PolicySender<String>>,
+    pub output: Arc<Mutex<PolicyReceiver<String>>>,
+    pub(crate) channels: ChannelsCfg,
+    stop: Mutex<Option<tokio::sync::oneshot::Sender<Option<Duration>>>>,
     exit: Mutex<tokio::sync::oneshot::Receiver<ExitStatus>>,
 }
 
 impl Runner {
     /// 启动服务器
-    pub async fn spawn_server(server: &dyn McServer) -> Result<Self> {
+    pub async fn spawn_server(server: &dyn McServer, channels: ChannelsCfg) -> Result<Self> {
         let mut child = server
             .start()?
             .stdin(Stdio::piped())
@@ -40,9 +63,9 @@ impl Runner {
         let id = NEXT_RUNNER_ID.fetch_add(1, Ordering::Relaxed);
 
         // stdin: 外部 -> child
-        let (stdin_tx, mut stdin_rx) = channel::<String>(32);
+        let (stdin_tx, stdin_rx) = policy_channel::<String>(channels.input);
         // stdout: child -> 外部
-        let (stdout_tx, stdout_rx) = channel(32);
+        let (stdout_tx, stdout_rx) = policy_channel::<String>(channels.output);
 
         let mut child_stdin = child.stdin.take().context("child stdin not piped")?;
         let child_stdout = child.stdout.take().context("child stdout not piped")?;
@@ -59,10 +82,10 @@ impl Runner {
         }
 
         // Child stdout -> tx
-        let stdout_rx = Arc::new(Mutex::new(stdout_rx));
-        let mut lines = BufReader::new(child_stdout).lines();
+        let mut reader = BufReader::new(child_stdout);
+        let mut buf = Vec::new();
         // 发送输出到管道
-        async fn send_output(line: String, stdout_tx: &Sender<String>) -> Result<()> {
+        async fn send_output(line: String, stdout_tx: &PolicySender<String>) -> Result<()> {
             let start = tokio::time::Instant::now();
             match stdout_tx.send(line).await {
                 Ok(_) => {
@@ -82,22 +105,26 @@ impl Runner {
         let stdin_tx = Arc::new(stdin_tx);
         let stdin_tx_clone = Arc::clone(&stdin_tx);
         // 根据信号退出
-        async fn stop(child: &mut Child, stdin_tx: Arc<Sender<String>>, time: Duration) {
+        async fn stop(
+            child: &mut Child,
+            stdin_tx: Arc<PolicySender<String>>,
+            time: Option<Duration>,
+        ) {
             // 发出退出信号
             let _ = stdin_tx.send("stop\n".into()).await;
+            let Some(time) = time else {
+                warn!("Waiting for the server to stop, it will not be killed");
+                watch(child).await;
+                return;
+            };
             // 给时间优雅退出
-            let graceful = timeout(time, async {
-                loop {
-                    if let Ok(Some(_)) = child.try_wait() {
-                        return;
-                    }
-                    sleep(Duration::from_millis(200)).await;
-                }
-            })
-            .await
-            .is_ok();
+            let graceful = timeout(time, watch(child)).await.is_ok();
             // 强制退出
             if !graceful {
+                warn!(
+                    "The server did not stop within {}s, killing it",
+                    time.as_secs()
+                );
                 let _ = child.start_kill();
             }
         }
@@ -116,7 +143,7 @@ impl Runner {
             .spawn_with_cancel(async move |t| {
                 loop {
                     select! {
-                        Some(line) = stdin_rx.recv() => recv_input(line, &mut child_stdin).await?,
+                        Some(line) = async { stdin_rx.lock().await.recv().await } => recv_input(line, &mut child_stdin).await?,
                         _ = t.cancelled() => break,
                     }
                 }
@@ -128,7 +155,7 @@ impl Runner {
             .spawn_with_cancel(async move |t| {
                 loop {
                     select! {
-                        Ok(Some(line)) = lines.next_line() => send_output(line,&stdout_tx ).await?,
+                        Ok(Some(line)) = next_line(&mut reader, &mut buf) => send_output(line,&stdout_tx ).await?,
                         _ = t.cancelled() => break
                     }
                 }
@@ -152,13 +179,14 @@ impl Runner {
             id,
             input: stdin_tx,
             output: stdout_rx,
+            channels,
             stop: Mutex::new(Some(stop_tx)),
             exit: Mutex::new(exit_rx),
         })
     }
 
-    /// 优雅停机，只应调用一次
-    pub async fn kill_with_timeout(&self, timeout: Duration) -> Result<()> {
+    /// 优雅停机，超时后强制结束，timeout 为 None 时一直等待，只应调用一次
+    pub async fn kill_with_timeout(&self, timeout: Option<Duration>) -> Result<()> {
         self.stop
             .lock()
             .await
@@ -177,36 +205,79 @@ impl Runner {
     }
 }
 
+/// 读取一行输出，统一去掉 CRLF/LF，非 UTF-8 的内容按有损方式转换
+async fn next_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> tokio::io::Result<Option<String>> {
+    buf.clear();
+    if reader.read_until(b'\n', buf).await? == 0 {
+        return Ok(None);
+    }
+    let line = match String::from_utf8_lossy(buf) {
+        std::borrow::Cow::Borrowed(line) => line.to_string(),
+        std::borrow::Cow::Owned(line) => {
+            debug!("Server output is not valid UTF-8, invalid bytes replaced");
+            line
+        }
+    };
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
 /// 将 IO 同步到控制台，此操作会独占 output
 pub async fn sync_channel_stdio(
-    input: Arc<Sender<String>>,
-    output: Arc<Mutex<Receiver<String>>>,
+    input: Arc<PolicySender<String>>,
+    output: Arc<Mutex<PolicyReceiver<String>>>,
     t: CancellationToken,
 ) -> Result<()> {
-    let mut stdin = fuck_tokio::AsyncStdin::new();
+    // 标准输入的读取线程在多次同步间共用，重启服务端时不会丢失输入
+    static STDIN: LazyLock<Mutex<fuck_tokio::AsyncStdin>> =
+        LazyLock::new(|| Mutex::new(fuck_tokio::AsyncStdin::new()));
+    let mut stdin = STDIN.lock().await;
 
-    async fn pump_stdin(input: Arc<Sender<String>>, stdin: &mut fuck_tokio::AsyncStdin) {
+    /// 返回 false 表示标准输入已关闭
+    async fn pump_stdin(
+        input: Arc<PolicySender<String>>,
+        stdin: &mut fuck_tokio::AsyncStdin,
+    ) -> bool {
         match stdin.next().await {
             Some(line) => {
                 if input.send(line.add("\n")).await.is_err() {
                     debug!("stdin -> channel failed: receiver dropped");
                 }
+                true
             }
             None => {
-                error!("stdin read thread stopped");
-                sleep(Duration::from_millis(200)).await;
+                debug!("stdin closed");
+                false
             }
-        };
+        }
     }
 
-    async fn pump_stdout(output: Arc<Mutex<Receiver<String>>>) {
-        match output.lock().await.recv().await {
+    async fn pump_stdout(output: Arc<Mutex<PolicyReceiver<String>>>) {
+        /// 单次合并写入的最大行数
+        const MAX_BATCH: usize = 256;
+        let mut output = output.lock().await;
+        match output.recv().await {
             Some(line) => {
-                let line = crate::util::highlighter::HIGHLIGHTER.apply(&*line);
-                match tokio::io::stdout()
-                    .write_all(line.add("\n").as_bytes())
-                    .await
-                {
+                // 合并已到达的输出，减少写入和刷新的次数
+                let mut buf = String::new();
+                let mut push = |line: String| {
+                    buf.push_str(&crate::util::highlighter::highlight(&line));
+                    buf.push('\n');
+                };
+                push(line);
+                for _ in 1..MAX_BATCH {
+                    match output.try_recv() {
+                        Ok(line) => push(line),
+                        Err(_) => break,
+                    }
+                }
+                // 交互式控制台需要在提示符上方输出
+                let Some(buf) = console::print(buf) else {
+                    return;
+                };
+                match tokio::io::stdout().write_all(buf.as_bytes()).await {
                     Err(e) => {
                         error!("Stdout write error {e}")
                     }
@@ -222,9 +293,11 @@ pub async fn sync_channel_stdio(
     }
 
     trace!("IO syncing");
+    // 标准输入关闭后不再轮询，只继续同步输出
+    let mut stdin_open = true;
     loop {
         select! {
-            _ = pump_stdin(input.clone(),&mut stdin) => {}
+            open = pump_stdin(input.clone(), &mut stdin), if stdin_open => stdin_open = open,
             _ = pump_stdout(output.clone()) => {}
             _ = t.cancelled() => break Ok(())
         }
@@ -238,6 +311,7 @@ pub async fn sync_channel_stdio(
 /// 确保程序优雅退出
 mod fuck_tokio {
     use crate::TASK_MANAGER;
+    use crate::core::mc_server::console;
     use futures::Stream;
     use futures::task::AtomicWaker;
     use std::io::BufRead;
@@ -267,8 +341,27 @@ mod fuck_tokio {
             }
         }
         fn thread(tx: Sender<String>, waker: Arc<AtomicWaker>) {
-            let mut stdin = BufReader::new(std::io::stdin()).lines();
-            while let Some(Ok(line)) = stdin.next() {
+            if console::is_interactive() {
+                console::read_lines(|line| {
+                    let sent = tx.send(line).is_ok();
+                    waker.wake();
+                    sent && !TASK_MANAGER.cancel_token.is_cancelled()
+                });
+                drop(tx);
+                waker.wake();
+                return;
+            }
+            let mut stdin = BufReader::new(std::io::stdin());
+            let mut buf = Vec::new();
+            // 按行读取，非 UTF-8 输入不会中断读取
+            while let Ok(n) = stdin.read_until(b'\n', &mut buf) {
+                if n == 0 {
+                    break;
+                }
+                let line = String::from_utf8_lossy(&buf)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string();
+                buf.clear();
                 if let Err(e) = tx.send(line) {
                     error!("Error send: {}", e)
                 } else {
@@ -278,6 +371,9 @@ mod fuck_tokio {
                     return;
                 }
             }
+            // 通知读取端标准输入已关闭
+            drop(tx);
+            waker.wake();
         }
     }
     impl Stream for AsyncStdin {
@@ -303,3 +399,123 @@ mod fuck_tokio {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn lines(output: &[u8]) -> Vec<String> {
+        let mut reader = BufReader::new(output);
+        let mut buf = Vec::new();
+        let mut lines = Vec::new();
+        while let Some(line) = next_line(&mut reader, &mut buf).await.unwrap() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Windows 下服务端输出 CRLF
+    #[tokio::test]
+    async fn crlf_is_stripped() {
+        assert_eq!(
+            lines(b"[Server] Starting\r\n[Server] Done\r\n\r\nlast").await,
+            ["[Server] Starting", "[Server] Done", "", "last"]
+        );
+        assert_eq!(lines(b"a\nb\r\n").await, ["a", "b"]);
+    }
+
+    /// 例如插件以 GBK 输出的中文，不应中断后续的输出
+    #[tokio::test]
+    async fn invalid_utf8_is_replaced() {
+        assert_eq!(
+            lines(b"\xc4\xe3\xba\xc3\r\n\xe4\xbd\xa0\xe5\xa5\xbd\r\n").await,
+            ["\u{FFFD}\u{FFFD}\u{FFFD}", "你好"]
+        );
+    }
+
+    #[cfg(unix)]
+    mod mock {
+        use super::super::*;
+        use crate::core::mc_server::mock::{DONE, MockServer, read_until};
+        use crate::util::testing::block_on;
+        use std::path::Path;
+
+        #[test]
+        fn spawn_server_forwards_io() {
+            block_on(async {
+                let server = MockServer::with_stop_delay(Path::new("server.jar"), Duration::ZERO);
+                let runner = Runner::spawn_server(&server, ChannelsCfg::default())
+                    .await
+                    .unwrap();
+                assert_eq!(read_until(&runner, "Done").await.last().unwrap(), DONE);
+
+                runner.input.send("list\n".to_string()).await.unwrap();
+                assert_eq!(read_until(&runner, "list").await, ["> list"]);
+
+                runner
+                    .kill_with_timeout(Some(Duration::from_secs(5)))
+                    .await
+                    .unwrap();
+                assert!(runner.wait().await.unwrap().success());
+            })
+        }
+
+        #[test]
+        fn graceful_stop_waits_for_the_server() {
+            block_on(async {
+                let server = MockServer::with_stop_delay(
+                    Path::new("server.jar"),
+                    Duration::from_millis(300),
+                );
+                let runner = Runner::spawn_server(&server, ChannelsCfg::default())
+                    .await
+                    .unwrap();
+                read_until(&runner, "Done").await;
+
+                runner
+                    .kill_with_timeout(Some(Duration::from_secs(5)))
+                    .await
+                    .unwrap();
+                assert_eq!(read_until(&runner, "Stopping").await.len(), 1);
+                assert!(runner.wait().await.unwrap().success());
+                // 停止信号只能发送一次
+                assert!(runner.kill_with_timeout(None).await.is_err());
+            })
+        }
+
+        #[test]
+        fn slow_server_is_killed_after_the_timeout() {
+            block_on(async {
+                let server =
+                    MockServer::with_stop_delay(Path::new("server.jar"), Duration::from_secs(30));
+                let runner = Runner::spawn_server(&server, ChannelsCfg::default())
+                    .await
+                    .unwrap();
+                read_until(&runner, "Done").await;
+
+                let start = tokio::time::Instant::now();
+                runner
+                    .kill_with_timeout(Some(Duration::from_millis(500)))
+                    .await
+                    .unwrap();
+                assert!(!runner.wait().await.unwrap().success());
+                assert!(start.elapsed() < Duration::from_secs(10));
+            })
+        }
+
+        #[test]
+        fn never_force_kill_waits_for_the_server() {
+            block_on(async {
+                let server =
+                    MockServer::with_stop_delay(Path::new("server.jar"), Duration::from_secs(1));
+                let runner = Runner::spawn_server(&server, ChannelsCfg::default())
+                    .await
+                    .unwrap();
+                read_until(&runner, "Done").await;
+
+                runner.kill_with_timeout(None).await.unwrap();
+                assert!(runner.wait().await.unwrap().success());
+            })
+        }
+    }
+}
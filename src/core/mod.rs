@@ -1,5 +1,9 @@
 pub mod arguments;
 pub mod backup;
 pub mod config;
+pub mod core_history;
+pub mod crash_report;
+pub mod hibernate;
 pub mod mc_server;
+pub mod restart;
 pub mod task;
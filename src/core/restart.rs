@@ -0,0 +1,173 @@
+// 定时重启
+
+use crate::core::arguments::ping::local_address;
+use crate::core::mc_server::runner::Runner;
+use crate::util::slp;
+use chrono::Utc;
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+/// 定时重启配置
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RestartCfg {
+    /// 重启计划，未设置时不重启
+    pub cron: Option<Schedule>,
+    /// 重启前发送的命令，例如 "say Restarting in 60 seconds"
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// 发送提醒后等待的秒数
+    #[serde(default = "default_warning_delay")]
+    pub warning_delay_secs: u64,
+    /// 没有玩家在线时跳过本次重启
+    #[serde(default)]
+    pub skip_if_empty: bool,
+}
+
+fn default_warning_delay() -> u64 {
+    60
+}
+
+impl Default for RestartCfg {
+    fn default() -> Self {
+        RestartCfg {
+            cron: None,
+            warnings: Vec::new(),
+            warning_delay_secs: default_warning_delay(),
+            skip_if_empty: false,
+        }
+    }
+}
+
+impl RestartCfg {
+    /// 等待到需要重启的时间，并向服务端发送提醒，未配置计划时永不返回
+    pub async fn wait(&self, runner: &Runner) {
+        let Some(schedule) = &self.cron else {
+            return std::future::pending().await;
+        };
+        loop {
+            let Some(next) = schedule.upcoming(Utc).next() else {
+                return std::future::pending().await;
+            };
+            let delay = (next - Utc::now()).to_std().unwrap_or_default();
+            debug!("Next scheduled restart at {next}");
+            sleep(delay).await;
+
+            if self.skip_if_empty && players_online().await == Some(0) {
+                info!("No players online, scheduled restart skipped");
+                continue;
+            }
+            break;
+        }
+
+        if !self.warnings.is_empty() {
+            for command in &self.warnings {
+                if runner.input.send(format!("{command}\n")).await.is_err() {
+                    return;
+                }
+            }
+            sleep(Duration::from_secs(self.warning_delay_secs)).await;
+        }
+        info!("Scheduled restart");
+    }
+}
+
+/// 通过 Server List Ping 获取在线人数，查询失败时为 None
+async fn players_online() -> Option<u32> {
+    let (host, port) = local_address().await;
+    match slp::ping(&host, port, Duration::from_secs(5)).await {
+        Ok(status) => Some(status.online),
+        Err(e) => {
+            warn!("Unable to query the online players: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_config() {
+        let cfg: RestartCfg = toml::from_str(
+            r#"
+            cron = "0 0 4 * * *"
+            warnings = ["say Restarting in 60 seconds"]
+            "#,
+        )
+        .unwrap();
+        let next = cfg.cron.unwrap().upcoming(Utc).next().unwrap();
+        assert_eq!(next.format("%H:%M:%S").to_string(), "04:00:00");
+        assert_eq!(cfg.warnings, ["say Restarting in 60 seconds"]);
+        assert_eq!(cfg.warning_delay_secs, 60);
+        assert!(!cfg.skip_if_empty);
+
+        assert!(toml::from_str::<RestartCfg>(r#"cron = "every day""#).is_err());
+    }
+
+    #[cfg(unix)]
+    mod mock {
+        use super::super::*;
+        use crate::core::mc_server::mock::{MockServer, read_until};
+        use crate::core::mc_server::runner::ChannelsCfg;
+        use crate::util::testing::block_on;
+        use std::path::Path;
+        use std::str::FromStr;
+        use tokio::time::timeout;
+
+        async fn runner() -> Runner {
+            let server = MockServer::with_stop_delay(Path::new("server.jar"), Duration::ZERO);
+            let runner = Runner::spawn_server(&server, ChannelsCfg::default())
+                .await
+                .unwrap();
+            read_until(&runner, "Done").await;
+            runner
+        }
+
+        async fn stop(runner: Runner) {
+            runner
+                .kill_with_timeout(Some(Duration::from_secs(5)))
+                .await
+                .unwrap();
+            runner.wait().await.unwrap();
+        }
+
+        #[test]
+        fn without_schedule_never_restarts() {
+            block_on(async {
+                let runner = runner().await;
+                let cfg = RestartCfg::default();
+                assert!(
+                    timeout(Duration::from_millis(500), cfg.wait(&runner))
+                        .await
+                        .is_err()
+                );
+                stop(runner).await;
+            })
+        }
+
+        #[test]
+        fn warns_then_restarts_on_schedule() {
+            block_on(async {
+                let runner = runner().await;
+                let cfg = RestartCfg {
+                    cron: Some(Schedule::from_str("* * * * * *").unwrap()),
+                    warnings: vec!["say Restarting".to_string()],
+                    warning_delay_secs: 0,
+                    skip_if_empty: false,
+                };
+                timeout(Duration::from_secs(5), cfg.wait(&runner))
+                    .await
+                    .expect("the restart was not triggered");
+                assert_eq!(
+                    read_until(&runner, "Restarting").await,
+                    ["> say Restarting"]
+                );
+                stop(runner).await;
+            })
+        }
+    }
+}
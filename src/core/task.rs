@@ -97,18 +97,15 @@ impl TaskManager {
         tasks.shutdown().await;
     }
 
-    /// 优雅 shutdown TaskManager
+    /// 优雅 shutdown TaskManager，重复调用不会产生效果
     pub async fn shutdown(&self) {
         trace!("Shutdown: Start");
         self.cancel_token.cancel();
-        let _ = self
-            .manager
-            .lock()
-            .await
-            .take()
-            .expect("No join handle")
-            .await
-            .inspect_err(|e| error!("{e}"));
+        let Some(handle) = self.manager.lock().await.take() else {
+            trace!("Shutdown: Already finished");
+            return;
+        };
+        let _ = handle.await.inspect_err(|e| error!("{e}"));
         debug!("Shutdown: Finish");
     }
 }
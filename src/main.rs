@@ -13,33 +13,49 @@ mod util;
 mod versions;
 
 use crate::core::arguments;
+use crate::core::arguments::player_list::PlayerList;
 use crate::core::backup::BackupManager;
+use crate::core::config::global::GlobalConfig;
+use crate::core::config::storage::StorageCfg;
 use crate::core::task::TaskManager;
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
 use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{Layer, Registry};
+use tracing_subscriber::{EnvFilter, Layer, Registry};
 
 // 创建任务管理器
 pub static TASK_MANAGER: LazyLock<TaskManager> = LazyLock::new(|| TaskManager::new());
 // 创建备份管理器
 pub static BACKUP_MANAGER: LazyLock<BackupManager> = LazyLock::new(|| BackupManager::new());
 
+// 全局配置
+pub static GLOBAL_CONFIG: LazyLock<GlobalConfig> = LazyLock::new(GlobalConfig::load);
+
+// 离线模式，只使用已缓存的文件
+pub static OFFLINE: AtomicBool = AtomicBool::new(false);
+
 // 全局缓存目录
 pub static GLOBAL_CACHE: LazyLock<PathBuf> = LazyLock::new(|| {
-    let path = std::env::home_dir().unwrap().join(".toymine").join("cache");
+    let path = StorageCfg::current().cache_root();
+    std::fs::create_dir_all(&path).expect("Failed to creat cache directory");
+    path
+});
+// 本次运行的下载目录，退出时清理
+pub static RUN_CACHE: LazyLock<PathBuf> = LazyLock::new(|| {
+    let path = GLOBAL_CACHE.join(format!("run-{}", uuid::Uuid::new_v4()));
     std::fs::create_dir_all(&path).expect("Failed to creat cache directory");
     path
 });
 // 共享运行时目录
 pub static GLOBAL_RUNTIME: LazyLock<PathBuf> = LazyLock::new(|| {
-    let path = std::env::home_dir()
-        .unwrap()
-        .join(".toymine")
-        .join("runtime");
+    let path = StorageCfg::current().runtime_root();
     std::fs::create_dir_all(&path).expect("Failed to creat runtime directory");
     path
 });
@@ -49,6 +65,21 @@ pub static GLOBAL_RUNTIME: LazyLock<PathBuf> = LazyLock::new(|| {
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Proxy for all network requests, overrides download.proxy and the proxy environment variables
+    #[arg(long, global = true)]
+    proxy: Option<String>,
+    /// Never access the network, only use cached files
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Show less output, -q for warnings only and -qq for errors only
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "verbose")]
+    quiet: u8,
+    /// Show more output, -v for debug and -vv for trace logs
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
+    /// Print the result or the error as JSON, logs are written to stderr
+    #[arg(long, global = true)]
+    json: bool,
 }
 
 #[derive(Subcommand)]
@@ -67,13 +98,203 @@ enum Commands {
     },
     /// Print the project information of the current location
     Info,
+    /// Create a project from the server file in the current location
+    Init {
+        /// Project name, defaults to the directory name
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Project description
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+    /// Adopt an existing server directory, or unpack an exported project
+    Import {
+        /// Server directory or .tar.zst archive
+        path: PathBuf,
+        /// Project name, defaults to the directory name
+        #[arg(short, long)]
+        name: Option<String>,
+        /// Directory to unpack an archive into, defaults to the archive name
+        #[arg(long)]
+        into: Option<PathBuf>,
+    },
+    /// Pack the project into a .tar.zst archive, without backups, logs and caches
+    Export {
+        /// Output archive
+        out: PathBuf,
+    },
+    /// Install the latest or the specified server version
+    Upgrade {
+        /// Target version, e.g. 1.21.1 or java:paper:1.21.1
+        version: Option<String>,
+        /// Allow installing an older version, which may corrupt the world
+        #[arg(long)]
+        allow_downgrade: bool,
+        /// Allow installing a snapshot, pre-release or release candidate
+        #[arg(long)]
+        accept_experimental: bool,
+        /// Do not start the new version to check that it works
+        #[arg(long)]
+        skip_smoke_test: bool,
+        /// Seconds to wait for the new version to finish starting
+        #[arg(long, default_value_t = 300)]
+        smoke_timeout: u64,
+        /// Expected sha1 or sha256 of the new server core
+        #[arg(long, value_name = "HASH")]
+        verify: Option<String>,
+    },
+    /// Restore a previously installed server core
+    Rollback {
+        /// Version to restore, defaults to the most recent previous core
+        version: Option<String>,
+        /// List the saved cores
+        #[arg(short, long)]
+        list: bool,
+    },
+    /// Remove old logs, caches, backup files and broken runtimes
+    Clean {
+        /// Remove archived logs in the logs directory
+        #[arg(long)]
+        logs: bool,
+        /// Remove the download cache
+        #[arg(long)]
+        cache: bool,
+        /// Remove .bak files older than the given number of days
+        #[arg(long, value_name = "DAYS")]
+        backups_older_than: Option<u64>,
+        /// Remove runtimes that were not installed completely
+        #[arg(long)]
+        runtimes: bool,
+        /// Only show what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Read or change a value in ToyMine.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Query a running Java server with the Server List Ping protocol
+    Ping {
+        /// host[:port], defaults to the address in server.properties
+        address: Option<String>,
+    },
+    /// Manage the backups of the project
+    Backup {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    /// Manage the whitelist while the server is stopped
+    Whitelist {
+        #[command(subcommand)]
+        action: PlayerListAction,
+    },
+    /// Manage the operators while the server is stopped
+    Op {
+        #[command(subcommand)]
+        action: PlayerListAction,
+    },
+    /// Print the completion script for a shell
+    #[command(hide = true)]
+    Completion {
+        /// bash, zsh, fish, powershell or elvish
+        shell: clap_complete::Shell,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Create a snapshot immediately
+    Now {
+        /// Back up only the world directories named by level-name in server.properties
+        #[arg(long, conflicts_with = "all")]
+        world_only: bool,
+        /// Back up the whole project except logs, caches and the backup repository
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PlayerListAction {
+    /// Add players, online-mode servers look up their UUID from Mojang
+    Add {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Remove players
+    Remove {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// List the players
+    List,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print a value, e.g. backup.option.on_stop
+    Get {
+        /// Dotted key path
+        key: String,
+    },
+    /// Change an existing value, checking its type and the resulting config
+    Set {
+        /// Dotted key path
+        key: String,
+        /// New value as a TOML literal, strings may be unquoted
+        value: String,
+    },
+}
+
+fn main() -> ExitCode {
+    // 参数解析
+    let cli = Cli::parse();
+
+    // 配置代理，修改环境变量必须在创建运行时和日志线程之前
+    let proxy = cli
+        .proxy
+        .as_deref()
+        .or(GLOBAL_CONFIG.download.proxy.as_deref());
+    // SAFETY: 此时只有主线程
+    unsafe { util::downloader::setup_proxy(proxy) };
+
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build the runtime")
+        .block_on(run(cli))
 }
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> Result<()> {
-    // 初始化日志
+async fn run(cli: Cli) -> ExitCode {
+    util::output::set_json(cli.json);
+    OFFLINE.store(cli.offline, Ordering::Relaxed);
+
+    // 初始化日志，-q/-v 优先于 RUST_LOG，默认为 INFO
+    let level = match (cli.quiet, cli.verbose) {
+        (0, 0) => None,
+        (1, _) => Some(LevelFilter::WARN),
+        (_, 0) => Some(LevelFilter::ERROR),
+        (_, 1) => Some(LevelFilter::DEBUG),
+        (_, _) => Some(LevelFilter::TRACE),
+    };
+    let filter = match level {
+        Some(level) => EnvFilter::new(level.to_string()),
+        None => EnvFilter::builder()
+            .with_default_directive(LevelFilter::INFO.into())
+            .from_env_lossy(),
+    };
+    let color = util::highlighter::setup_color();
+    // JSON 模式下 stdout 只用于输出结果
+    let writer = if cli.json {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
     let fmt_layer = tracing_subscriber::fmt::Layer::default()
-        .with_filter(tracing_subscriber::filter::LevelFilter::INFO);
+        .with_ansi(color)
+        .with_writer(writer)
+        .with_filter(filter);
 
     #[cfg(not(feature = "telemetry"))]
     let subscriber_builder = Registry::default().with(fmt_layer);
@@ -104,20 +325,88 @@ async fn main() -> Result<()> {
     };
 
     subscriber_builder.init();
+    GlobalConfig::warn_if_invalid();
 
     #[cfg(feature = "telemetry")]
     dhat::Profiler::new_heap();
 
-    // 参数解析
-    let cli = Cli::parse();
-
-    match cli.command {
+    let result = match cli.command {
         Commands::Start {
             generate,
             detach,
             attach,
-        } => arguments::start::start(generate, detach, attach).await?,
-        Commands::Info => arguments::info::info().await?,
+        } => arguments::start::start(generate, detach, attach).await,
+        Commands::Info => arguments::info::info().await,
+        Commands::Init { name, description } => arguments::init::init(name, description).await,
+        Commands::Import { path, name, into } => arguments::import::import(path, name, into).await,
+        Commands::Export { out } => arguments::export::export(out).await,
+        Commands::Upgrade {
+            version,
+            allow_downgrade,
+            accept_experimental,
+            skip_smoke_test,
+            smoke_timeout,
+            verify,
+        } => {
+            arguments::upgrade::upgrade(
+                version,
+                allow_downgrade,
+                accept_experimental,
+                skip_smoke_test,
+                smoke_timeout,
+                verify,
+            )
+            .await
+        }
+        Commands::Rollback { version, list } => arguments::rollback::rollback(version, list).await,
+        Commands::Clean {
+            logs,
+            cache,
+            backups_older_than,
+            runtimes,
+            dry_run,
+        } => {
+            arguments::clean::clean(arguments::clean::CleanOptions {
+                logs,
+                cache,
+                backups_older_than,
+                runtimes,
+                dry_run,
+            })
+            .await
+        }
+        Commands::Ping { address } => arguments::ping::ping(address).await,
+        Commands::Backup { action } => match action {
+            BackupAction::Now { world_only, all } => arguments::backup::now(world_only, all).await,
+        },
+        Commands::Whitelist { action } => player_list(PlayerList::Whitelist, action).await,
+        Commands::Op { action } => player_list(PlayerList::Op, action).await,
+        Commands::Completion { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+            Ok(())
+        }
+        Commands::Config { action } => match action {
+            ConfigAction::Get { key } => arguments::config::get(&key).await,
+            ConfigAction::Set { key, value } => arguments::config::set(&key, &value).await,
+        },
+    };
+
+    // 确保所有任务在退出前结束
+    TASK_MANAGER.shutdown().await;
+    core::mc_server::console::restore_terminal();
+
+    // 只清理本次运行的下载目录，不影响其他进程
+    let _ = tokio::fs::remove_dir_all(&*RUN_CACHE).await;
+
+    util::output::finish(result)
+}
+
+async fn player_list(list: PlayerList, action: PlayerListAction) -> Result<()> {
+    match action {
+        PlayerListAction::Add { names } => arguments::player_list::add(list, names).await,
+        PlayerListAction::Remove { names } => arguments::player_list::remove(list, names).await,
+        PlayerListAction::List => arguments::player_list::list(list).await,
     }
-    Ok(())
 }
@@ -1,6 +1,6 @@
-use crate::util::downloader::Downloader;
 use crate::GLOBAL_RUNTIME;
-use anyhow::{Error, Result};
+use crate::util::downloader::{Downloader, is_offline};
+use anyhow::{Error, Result, anyhow};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
@@ -39,31 +39,33 @@ impl GeneralJavaRuntimeManager {
             }]),
         }
     }
+    /// 获取可用的运行时，返回 java 可执行文件的路径
     pub async fn check(&self, version: usize) -> Vec<(PathBuf, JavaType)> {
         self.list
             .lock()
             .await
             .iter()
             .filter(|&x| x.version == version)
-            .filter(|&x| !x.installing.try_lock().is_ok())
-            .map(|x| (x.java_home.clone(), x.distribution.clone()))
+            .filter(|&x| x.installing.try_lock().is_ok())
+            .filter_map(|x| Some((java_binary(&x.java_home)?, x.distribution.clone())))
             .collect()
     }
+    /// 安装运行时，返回 java 可执行文件的路径
     pub async fn install(&self, version: usize) -> Result<PathBuf> {
         // GraalVM Test
         let install_lock = Arc::new(Mutex::new(()));
-        let path = GLOBAL_RUNTIME.join(format!(
-            "graalvm-jdk-{}-{}-{}",
-            version,
-            std::env::consts::OS,
-            std::env::consts::ARCH
-        ));
+        let path = graal_home(version);
         let mut guard = self.list.lock().await;
         match guard
             .iter()
             .find(|&x| x.distribution == JavaType::GraalVM && x.version == version)
         {
             None => {
+                if is_offline() {
+                    return Err(anyhow!(
+                        "Java {version} is not installed and cannot be downloaded in offline mode"
+                    ));
+                }
                 guard.push(JavaRuntime {
                     java_home: path.clone(),
                     distribution: JavaType::GraalVM,
@@ -73,15 +75,34 @@ impl GeneralJavaRuntimeManager {
             }
             Some(v) => {
                 let _ = v.installing.lock().await;
-                return Ok(v.java_home.clone());
+                return locate(&v.java_home);
             }
         };
         drop(guard);
         get_graal(version).await?;
-        Ok(path)
+        locate(&path)
     }
 }
 
+/// 在 JAVA_HOME 的常见布局中查找 java 可执行文件
+/// 依次尝试 `bin/java`、macOS 的 `Contents/Home/bin/java` 和 JRE 的 `jre/bin/java`
+pub fn java_binary(java_home: &Path) -> Option<PathBuf> {
+    let java = if cfg!(windows) { "java.exe" } else { "java" };
+    [
+        java_home.join("bin"),
+        java_home.join("Contents").join("Home").join("bin"),
+        java_home.join("jre").join("bin"),
+    ]
+    .into_iter()
+    .map(|bin| bin.join(java))
+    .find(|p| p.is_file())
+}
+
+/// 运行时目录中的 java 可执行文件，找不到时报错
+fn locate(java_home: &Path) -> Result<PathBuf> {
+    java_binary(java_home).ok_or_else(|| anyhow!("No java executable in {}", java_home.display()))
+}
+
 /// 拉平一层目录
 async fn flatten_single_child(dir: &Path) -> std::io::Result<()> {
     let mut rd = tokio::fs::read_dir(dir).await?;
@@ -110,17 +131,38 @@ async fn flatten_single_child(dir: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
+/// GraalVM 的安装目录
+fn graal_home(version: usize) -> PathBuf {
+    GLOBAL_RUNTIME.join(format!(
+        "graalvm-jdk-{}-{}-{}",
+        version,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    ))
+}
+
+/// 构造 GraalVM 的下载地址
+/// Oracle 使用 linux/macos/windows 和 x64/aarch64 作为平台标识
+fn graal_url(version: usize, os: &str, arch: &str) -> Result<String> {
+    let os_token = match os {
+        "linux" | "macos" | "windows" => os,
+        _ => return Err(anyhow!("GraalVM is not available for {os}")),
+    };
+    let arch_token = match arch {
+        "x86_64" => "x64",
+        "aarch64" => "aarch64",
+        _ => return Err(anyhow!("GraalVM is not available for {os}-{arch}")),
+    };
+    let extension = if os == "windows" { "zip" } else { "tar.gz" };
+    Ok(format!(
+        "https://download.oracle.com/graalvm/{version}/archive/graalvm-jdk-{version}_{os_token}-{arch_token}_bin.{extension}"
+    ))
+}
+
 /// 获取 GraalVM
 async fn get_graal(version: usize) -> Result<()> {
     info!("Start downloading GraalVM JDK {version}");
-    let extension = if cfg!(windows) { "zip" } else { "tar.gz" };
-    let url = format!(
-        "https://download.oracle.com/graalvm/{version}/archive/graalvm-jdk-{version}_{}-{}_bin.{extension}",
-        std::env::consts::OS,
-        std::env::consts::ARCH.replace("86_", ""),
-        version = version,
-        extension = extension
-    );
+    let url = graal_url(version, std::env::consts::OS, std::env::consts::ARCH)?;
     let hash = Downloader::new()
         .await
         .get(format!("{url}.sha256"))
@@ -129,44 +171,22 @@ async fn get_graal(version: usize) -> Result<()> {
         .await?;
     let file = Downloader::new()
         .await
-        .download_with_sha256(url, hash)
+        .download_with_sha256(url.clone(), hash)
         .await?;
     info!("Download complete. Start unzipping.");
-    let (file, path) = tokio::task::spawn_blocking(move || {
-        use std::io::{Read, Write};
-        let mut zip = zip::ZipArchive::new(std::fs::File::open(&file)?)?;
-        let path = GLOBAL_RUNTIME.join(format!(
-            "graalvm-jdk-{}-{}-{}",
-            version,
-            std::env::consts::OS,
-            std::env::consts::ARCH
-        ));
-        let pb = ProgressBar::new(zip.len() as u64);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("[{bar:40}] {pos}/{len} ({eta}) {msg}")
-                .unwrap()
-                .progress_chars("=>-"),
-        );
-        for i in 0..zip.len() {
-            let mut file = zip.by_index(i)?;
-            pb.set_message(format!("Unzipping {}", file.name()));
-            let out_path = path.join(file.name());
-            if file.is_dir() {
-                std::fs::create_dir_all(&out_path)?;
-                continue;
-            }
-            if let Some(parent) = out_path.parent() {
-                std::fs::create_dir_all(parent)?;
+    let path = graal_home(version);
+    let file = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || {
+            if url.ends_with(".zip") {
+                unzip(&file, &path)?;
+            } else {
+                // tar 包保留可执行权限，macOS 的包中 JDK 位于 Contents/Home
+                let gz = flate2::read::GzDecoder::new(std::fs::File::open(&file)?);
+                tar::Archive::new(gz).unpack(&path)?;
             }
-            let mut out_file = std::fs::File::create(&out_path)?;
-            let mut buffer = Vec::new();
-            let _ = file.read_to_end(&mut buffer);
-            out_file.write_all(&buffer)?;
-            pb.inc(1)
+            Ok::<PathBuf, Error>(file)
         }
-        pb.finish_with_message("done");
-        Ok::<(PathBuf, PathBuf), Error>((file, path))
     })
     .await??;
     flatten_single_child(&path).await?;
@@ -174,3 +194,141 @@ async fn get_graal(version: usize) -> Result<()> {
     tokio::fs::remove_file(file).await?;
     Ok(())
 }
+
+/// 解压 zip 包
+fn unzip(file: &Path, path: &Path) -> Result<()> {
+    use std::io::{Read, Write};
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(file)?)?;
+    let pb = ProgressBar::new(zip.len() as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{bar:40}] {pos}/{len} ({eta}) {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        pb.set_message(format!("Unzipping {}", file.name()));
+        let out_path = path.join(file.name());
+        if file.is_dir() {
+            std::fs::create_dir_all(&out_path)?;
+            continue;
+        }
+        if let Some(parent) = out_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut out_file = std::fs::File::create(&out_path)?;
+        let mut buffer = Vec::new();
+        let _ = file.read_to_end(&mut buffer);
+        out_file.write_all(&buffer)?;
+        pb.inc(1)
+    }
+    pb.finish_with_message("done");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::temp_dir;
+
+    /// 在 java_home 下按给定布局放置 java 可执行文件
+    fn java_home(layout: &[&str]) -> (PathBuf, PathBuf) {
+        let home = temp_dir();
+        let bin = layout.iter().fold(home.clone(), |p, c| p.join(c));
+        std::fs::create_dir_all(&bin).unwrap();
+        let java = bin.join(if cfg!(windows) { "java.exe" } else { "java" });
+        std::fs::write(&java, "").unwrap();
+        (home, java)
+    }
+
+    #[test]
+    fn finds_java_in_each_layout() {
+        for layout in [&["bin"][..], &["Contents", "Home", "bin"], &["jre", "bin"]] {
+            let (home, java) = java_home(layout);
+            assert_eq!(java_binary(&home), Some(java), "{layout:?}");
+            std::fs::remove_dir_all(home).unwrap();
+        }
+    }
+
+    #[test]
+    fn prefers_the_jdk_binary() {
+        let (home, java) = java_home(&["bin"]);
+        let jre = home.join("jre").join("bin");
+        std::fs::create_dir_all(&jre).unwrap();
+        std::fs::write(jre.join(java.file_name().unwrap()), "").unwrap();
+        assert_eq!(java_binary(&home), Some(java));
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_and_install_return_the_binary() {
+        let (home, java) = java_home(&["Contents", "Home", "bin"]);
+        let manager = GeneralJavaRuntimeManager {
+            list: Mutex::new(vec![JavaRuntime {
+                java_home: home.clone(),
+                distribution: JavaType::GraalVM,
+                version: 17,
+                installing: Arc::new(Default::default()),
+            }]),
+        };
+        let found = manager.check(17).await;
+        assert_eq!(found.first().map(|(p, _)| p), Some(&java));
+        assert!(manager.check(21).await.is_empty());
+        // 已安装的运行时不会重新下载
+        assert_eq!(manager.install(17).await.unwrap(), java);
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn missing_java_is_none() {
+        let home = temp_dir();
+        std::fs::create_dir_all(home.join("bin")).unwrap();
+        assert_eq!(java_binary(&home), None);
+        std::fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn graal_url_for_macos() {
+        assert_eq!(
+            graal_url(21, "macos", "x86_64").unwrap(),
+            "https://download.oracle.com/graalvm/21/archive/graalvm-jdk-21_macos-x64_bin.tar.gz"
+        );
+        assert_eq!(
+            graal_url(21, "macos", "aarch64").unwrap(),
+            "https://download.oracle.com/graalvm/21/archive/graalvm-jdk-21_macos-aarch64_bin.tar.gz"
+        );
+    }
+
+    #[test]
+    fn graal_url_for_other_platforms() {
+        assert_eq!(
+            graal_url(17, "windows", "x86_64").unwrap(),
+            "https://download.oracle.com/graalvm/17/archive/graalvm-jdk-17_windows-x64_bin.zip"
+        );
+        assert_eq!(
+            graal_url(17, "linux", "aarch64").unwrap(),
+            "https://download.oracle.com/graalvm/17/archive/graalvm-jdk-17_linux-aarch64_bin.tar.gz"
+        );
+        assert!(graal_url(21, "freebsd", "x86_64").is_err());
+        assert!(graal_url(21, "macos", "x86").is_err());
+    }
+
+    /// macOS 的包解压后为 jdk-21.jdk/Contents/Home/bin/java
+    #[tokio::test]
+    async fn macos_bundle_is_flattened() {
+        let (dir, _) = java_home(&["jdk-21.jdk", "Contents", "Home", "bin"]);
+        flatten_single_child(&dir).await.unwrap();
+        assert!(!dir.join("jdk-21.jdk").exists());
+        assert_eq!(
+            java_binary(&dir),
+            Some(
+                dir.join("Contents")
+                    .join("Home")
+                    .join("bin")
+                    .join(if cfg!(windows) { "java.exe" } else { "java" })
+            )
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
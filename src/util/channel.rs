@@ -0,0 +1,269 @@
+// 带溢出策略的有界通道
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::Notify;
+use tokio::sync::mpsc::error::{SendError, TryRecvError};
+use tracing::warn;
+
+/// 缓冲区满时的处理策略
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelPolicy {
+    /// 丢弃缓冲区中最旧的消息
+    DropOldest,
+    /// 丢弃新到达的消息
+    DropNewest,
+    /// 等待缓冲区有空位，不丢失消息
+    Block,
+}
+
+/// 通道配置
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ChannelCfg {
+    /// 缓冲区大小
+    pub size: usize,
+    /// 溢出策略
+    pub policy: ChannelPolicy,
+}
+
+impl ChannelCfg {
+    pub const fn new(size: usize, policy: ChannelPolicy) -> Self {
+        Self { size, policy }
+    }
+}
+
+/// 收发两端共享的缓冲区，队列锁只在入队/出队的瞬间持有，发送端不会等待接收端的锁
+struct Shared<T> {
+    queue: std::sync::Mutex<VecDeque<T>>,
+    size: usize,
+    /// 有新消息或发送端关闭
+    item: Notify,
+    /// 有空位或接收端关闭
+    space: Notify,
+    tx_closed: AtomicBool,
+    rx_closed: AtomicBool,
+}
+
+impl<T> Shared<T> {
+    fn queue(&self) -> std::sync::MutexGuard<'_, VecDeque<T>> {
+        // 队列操作不会在持锁时 panic，中毒时直接沿用内部数据
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// 按策略发送的 Sender
+pub struct PolicySender<T> {
+    shared: Arc<Shared<T>>,
+    policy: ChannelPolicy,
+    dropped: AtomicUsize,
+}
+
+/// 与 PolicySender 配对的 Receiver
+pub struct PolicyReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+/// 创建带溢出策略的通道
+pub fn policy_channel<T>(
+    cfg: ChannelCfg,
+) -> (PolicySender<T>, Arc<tokio::sync::Mutex<PolicyReceiver<T>>>) {
+    let size = cfg.size.max(1);
+    let shared = Arc::new(Shared {
+        queue: std::sync::Mutex::new(VecDeque::with_capacity(size)),
+        size,
+        item: Notify::new(),
+        space: Notify::new(),
+        tx_closed: AtomicBool::new(false),
+        rx_closed: AtomicBool::new(false),
+    });
+    (
+        PolicySender {
+            shared: Arc::clone(&shared),
+            policy: cfg.policy,
+            dropped: AtomicUsize::new(0),
+        },
+        Arc::new(tokio::sync::Mutex::new(PolicyReceiver { shared })),
+    )
+}
+
+impl<T> PolicySender<T> {
+    /// 按策略发送，仅在接收端关闭时返回错误
+    pub async fn send(&self, value: T) -> Result<(), SendError<T>> {
+        loop {
+            if self.shared.rx_closed.load(Ordering::Acquire) {
+                return Err(SendError(value));
+            }
+            {
+                let mut queue = self.shared.queue();
+                if queue.len() < self.shared.size {
+                    queue.push_back(value);
+                    drop(queue);
+                    self.shared.item.notify_one();
+                    return Ok(());
+                }
+                match self.policy {
+                    ChannelPolicy::DropNewest => {
+                        drop(queue);
+                        self.on_drop();
+                        return Ok(());
+                    }
+                    ChannelPolicy::DropOldest => {
+                        // 直接在缓冲区内淘汰最旧的消息，不经过接收端
+                        queue.pop_front();
+                        queue.push_back(value);
+                        drop(queue);
+                        self.on_drop();
+                        self.shared.item.notify_one();
+                        return Ok(());
+                    }
+                    ChannelPolicy::Block => {}
+                }
+            }
+            // notify_one 在无人等待时会保留许可，检查与等待之间腾出的空位不会丢失
+            self.shared.space.notified().await;
+        }
+    }
+
+    fn on_drop(&self) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        // 避免日志刷屏，仅在首次和每 1000 条时提示
+        if dropped == 1 || dropped.is_multiple_of(1000) {
+            warn!(
+                "Channel full, {} message(s) dropped ({:?})",
+                dropped, self.policy
+            );
+        }
+    }
+}
+
+impl<T> Drop for PolicySender<T> {
+    fn drop(&mut self) {
+        self.shared.tx_closed.store(true, Ordering::Release);
+        self.shared.item.notify_one();
+    }
+}
+
+impl<T> PolicyReceiver<T> {
+    /// 接收一条消息，发送端关闭且缓冲区为空时返回 None
+    pub async fn recv(&mut self) -> Option<T> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Some(value),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => self.shared.item.notified().await,
+            }
+        }
+    }
+
+    /// 非阻塞接收
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        // 先读关闭标记再取队列，避免漏掉关闭前最后入队的消息
+        let closed = self.shared.tx_closed.load(Ordering::Acquire);
+        match self.shared.queue().pop_front() {
+            Some(value) => {
+                self.shared.space.notify_one();
+                Ok(value)
+            }
+            None if closed => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+impl<T> Drop for PolicyReceiver<T> {
+    fn drop(&mut self) {
+        self.shared.rx_closed.store(true, Ordering::Release);
+        self.shared.space.notify_waiters();
+        self.shared.space.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    async fn fill(tx: &PolicySender<usize>, n: usize) {
+        for i in 0..n {
+            tx.send(i).await.unwrap();
+        }
+    }
+
+    fn dropped<T>(tx: &PolicySender<T>) -> usize {
+        tx.dropped.load(Ordering::Relaxed)
+    }
+
+    fn drain(rx: &mut PolicyReceiver<usize>) -> Vec<usize> {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    #[tokio::test]
+    async fn drop_newest_keeps_first_messages() {
+        let (tx, rx) = policy_channel(ChannelCfg::new(3, ChannelPolicy::DropNewest));
+        fill(&tx, 5).await;
+        assert_eq!(dropped(&tx), 2);
+        assert_eq!(drain(&mut *rx.lock().await), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_keeps_last_messages() {
+        let (tx, rx) = policy_channel(ChannelCfg::new(3, ChannelPolicy::DropOldest));
+        fill(&tx, 5).await;
+        assert_eq!(dropped(&tx), 2);
+        assert_eq!(drain(&mut *rx.lock().await), vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn drop_oldest_does_not_wait_for_a_held_receiver() {
+        let (tx, rx) = policy_channel(ChannelCfg::new(2, ChannelPolicy::DropOldest));
+        let mut guard = rx.lock().await;
+        timeout(Duration::from_secs(1), fill(&tx, 10))
+            .await
+            .expect("sender blocked on the receiver lock");
+        assert_eq!(dropped(&tx), 8);
+        assert_eq!(drain(&mut guard), vec![8, 9]);
+    }
+
+    #[tokio::test]
+    async fn block_waits_for_space_without_dropping() {
+        let (tx, rx) = policy_channel(ChannelCfg::new(2, ChannelPolicy::Block));
+        fill(&tx, 2).await;
+        assert!(
+            timeout(Duration::from_millis(50), tx.send(2))
+                .await
+                .is_err()
+        );
+
+        let tx = Arc::new(tx);
+        let sender = tokio::spawn({
+            let tx = Arc::clone(&tx);
+            async move { fill(&tx, 4).await }
+        });
+        let mut rx = rx.lock().await;
+        let mut received = Vec::new();
+        while received.len() < 6 {
+            received.push(rx.recv().await.unwrap());
+        }
+        sender.await.unwrap();
+        assert_eq!(dropped(&tx), 0);
+        assert_eq!(received, vec![0, 1, 0, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn closed_ends() {
+        let (tx, rx) = policy_channel(ChannelCfg::new(2, ChannelPolicy::Block));
+        tx.send(1).await.unwrap();
+        drop(tx);
+        let mut rx = rx.lock().await;
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(rx.recv().await, None);
+
+        let (tx, rx) = policy_channel::<usize>(ChannelCfg::new(2, ChannelPolicy::Block));
+        drop(rx);
+        assert!(tx.send(1).await.is_err());
+    }
+}
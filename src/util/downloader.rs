@@ -1,20 +1,24 @@
-use crate::GLOBAL_CACHE;
-use crate::util::hash::Sha256Digest;
+use crate::util::hash::{Checksum, Sha256Digest, file_digests};
+use crate::{GLOBAL_CONFIG, OFFLINE, RUN_CACHE};
 use anyhow::Result;
 use anyhow::anyhow;
-use futures::{StreamExt, stream};
-use indicatif::{ProgressBar, ProgressStyle};
+use futures::{TryStreamExt, stream};
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
 use nyquest::r#async::Response;
-use nyquest::{AsyncClient, Request};
+use nyquest::{AsyncClient, Request, StatusCode};
 use sha2::{Digest, Sha256};
 use std::borrow::Cow;
-use std::path::PathBuf;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::select;
 use tokio::sync::{Mutex, OnceCell};
+use tokio::time::timeout;
 use tokio_util::compat::FuturesAsyncReadCompatExt;
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
 pub struct Downloader {
     client: AsyncClient,
@@ -24,11 +28,39 @@ pub struct Downloader {
 const BLOCK_SIZE: u64 = 1024 * 1024;
 /// 单个文件最大连接数
 const CONCURRENCY: usize = 8;
-/// 最大重试次数
-const MAX_RETRY: usize = 3;
 
 static GLOBAL_DOWNLOADER: OnceCell<Downloader> = OnceCell::const_new();
 
+/// 配置 HTTP 客户端使用的代理
+///
+/// 优先级：`--proxy` 参数或全局配置 > `HTTPS_PROXY`/`HTTP_PROXY` > `ALL_PROXY`，`NO_PROXY` 照常生效。
+/// curl 后端只识别小写的 `http_proxy`/`https_proxy`，此处将其他变量同步为小写。
+/// Windows/macOS 后端使用系统代理设置。
+///
+/// # Safety
+/// 修改环境变量不是线程安全的，必须在创建运行时和其他线程之前调用
+pub unsafe fn setup_proxy(proxy: Option<&str>) {
+    let set = |key: &str, value: &str| {
+        // SAFETY: 由调用者保证此时没有其他线程
+        unsafe { std::env::set_var(key, value) }
+    };
+    if let Some(proxy) = proxy {
+        set("http_proxy", proxy);
+        set("https_proxy", proxy);
+        return;
+    }
+    let var = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+    let all = var("all_proxy").or_else(|| var("ALL_PROXY"));
+    for (upper, lower) in [("HTTP_PROXY", "http_proxy"), ("HTTPS_PROXY", "https_proxy")] {
+        if var(lower).is_some() {
+            continue;
+        }
+        if let Some(value) = var(upper).or_else(|| all.clone()) {
+            set(lower, &value);
+        }
+    }
+}
+
 impl Downloader {
     /// 获取下载器
     pub async fn new() -> &'static Downloader {
@@ -37,7 +69,7 @@ impl Downloader {
                 nyquest_preset::register();
 
                 let client = nyquest::ClientBuilder::default()
-                    .user_agent("curl/7.68.0 nyquest/0")
+                    .user_agent(GLOBAL_CONFIG.download.user_agent.as_str())
                     .build_async()
                     .await
                     .expect("Failed to build client");
@@ -46,73 +78,89 @@ impl Downloader {
             })
             .await
     }
+    /// 发送请求，超时、IO 错误和 5xx 响应会退避重试
+    pub async fn request(
+        &self,
+        request: impl Fn() -> nyquest::r#async::Request,
+    ) -> nyquest::Result<Response> {
+        if is_offline() {
+            return Err(nyquest::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                "Network access is disabled in offline mode",
+            )));
+        }
+        let max_attempts = GLOBAL_CONFIG.download.max_attempts();
+        let mut attempt = 1;
+        loop {
+            let result = self.client.request(request()).await;
+            let retryable = match &result {
+                Ok(resp) => is_transient_status(resp.status()),
+                Err(nyquest::Error::RequestTimeout | nyquest::Error::Io(_)) => true,
+                Err(_) => false,
+            };
+            if !retryable || attempt >= max_attempts {
+                return result;
+            }
+            let delay = backoff(attempt);
+            warn!(
+                "Request failed, retrying in {} ms ({}/{})",
+                delay.as_millis(),
+                attempt,
+                max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
     /// GET 请求
     pub async fn get(&self, uri: impl Into<Cow<'static, str>>) -> nyquest::Result<Response> {
-        self.client.request(Request::get(uri)).await
+        let uri = uri.into();
+        self.request(|| with_host_headers(Request::get(uri.clone()), &uri))
+            .await
     }
     /// 下载文件，自动启用多线程
     pub async fn download(&self, uri: impl Into<Cow<'static, str>>) -> Result<PathBuf> {
         let inner = Box::pin(async move {
+            if is_offline() {
+                return Err(anyhow!("Network access is disabled in offline mode"));
+            }
             let uri = Cow::clone(&uri.into());
-            // 获取文件信息
-            let head = self.client.request(Request::head(uri.clone())).await?;
-            let file_name = GLOBAL_CACHE.join(uuid::Uuid::new_v4().to_string());
+            // 获取文件信息，失败或超时时使用单线程下载
+            let head = self.head(&uri, GLOBAL_CONFIG.download.timeout()).await;
+            let file_name = RUN_CACHE.join(uuid::Uuid::new_v4().to_string());
             let mut file = tokio::fs::OpenOptions::new()
                 .create(true)
                 .write(true)
                 .open(&file_name)
                 .await?;
-            let support_range = matches!(
-                head.get_header("accept-ranges")?
-                    .first()
-                    .map(|t| t.as_str()),
-                Some("bytes")
-            );
+            let support_range = match &head {
+                Some(head) => matches!(
+                    head.get_header("accept-ranges")?
+                        .first()
+                        .map(|t| t.as_str()),
+                    Some("bytes")
+                ),
+                None => false,
+            };
             // 判断是否多线程下载
-            if let Some(total_size) = head.content_length()
+            let mut single = true;
+            if let Some(total_size) = head.as_ref().and_then(|h| h.content_length())
                 && support_range
             {
                 debug!("Multithreaded downloading");
-                // 设置进度条
-                let pb = ProgressBar::new(total_size);
-                pb.set_style(
-                    ProgressStyle::default_bar()
-                        .template("[{bar:40}] {binary_bytes}/{binary_total_bytes} {binary_bytes_per_sec} ({eta})")
-                        .unwrap()
-                        .progress_chars("=>-"),
-                );
-                // 计算分片
-                let split_ranges = (0..total_size).step_by(BLOCK_SIZE as usize).map(|start| {
-                    let end = (start + BLOCK_SIZE - 1).min(total_size - 1);
-                    (start, end)
-                });
-                file.set_len(total_size).await?;
-                let file = Arc::new(Mutex::new(file));
-                let pb = Arc::new(pb);
-                // 并发下载
-                stream::iter(split_ranges)
-                    .for_each_concurrent(CONCURRENCY, |(start, end)| {
-                        let file = file.clone();
-                        let uri = uri.clone();
-                        let pb = pb.clone();
-                        async move {
-                            for _ in 0..MAX_RETRY {
-                                if let Err(e) =
-                                    download_chunk(&self.client, uri.clone(), &file, start, end)
-                                        .await
-                                {
-                                    error!("chunk error: {e:?}");
-                                } else {
-                                    break;
-                                }
-                            }
-                            pb.inc(end - start)
-                        }
-                    })
-                    .await;
-                file.lock().await.flush().await?;
-                pb.finish_with_message("done");
-            } else {
+                match self
+                    .download_ranges(uri.clone(), file.try_clone().await?, total_size)
+                    .await
+                {
+                    Ok(_) => single = false,
+                    // 服务端声明支持分片却返回了完整内容，改用单线程下载
+                    Err(e) if e.downcast_ref::<RangeIgnored>().is_some() => {
+                        warn!("{e}, falling back to single-threaded download")
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if single {
                 debug!("Single-threaded downloading");
                 let pb = ProgressBar::new_spinner();
                 pb.set_style(
@@ -122,17 +170,35 @@ impl Downloader {
                 );
                 pb.set_message("Downloading...");
                 pb.enable_steady_tick(Duration::from_millis(100));
-                for _ in 0..MAX_RETRY {
-                    let mut stream = self
-                        .client
-                        .request(Request::get(uri.clone()))
-                        .await?
+                let max_attempts = GLOBAL_CONFIG.download.max_attempts();
+                let mut attempt = 1;
+                loop {
+                    // 每次重试从头写入，避免残留内容
+                    file.set_len(0).await?;
+                    file.seek(tokio::io::SeekFrom::Start(0)).await?;
+                    let result = async {
+                        let mut stream = timeout(
+                            GLOBAL_CONFIG.download.timeout(),
+                            self.client
+                                .request(with_host_headers(Request::get(uri.clone()), &uri)),
+                        )
+                        .await
+                        .map_err(|_| anyhow!("Timed out waiting for {uri}"))??
+                        .with_successful_status()?
                         .into_async_read()
                         .compat();
-                    if let Err(e) = tokio::io::copy(&mut stream, &mut file).await {
-                        error!("downloading error: {e:?}")
-                    } else {
-                        break;
+                        tokio::io::copy(&mut stream, &mut file).await?;
+                        anyhow::Ok(())
+                    }
+                    .await;
+                    match result {
+                        Ok(()) => break,
+                        Err(e) if attempt < max_attempts && is_transient(&e) => {
+                            error!("downloading error: {e:?}");
+                            tokio::time::sleep(backoff(attempt)).await;
+                            attempt += 1;
+                        }
+                        Err(e) => return Err(e),
                     }
                 }
                 pb.finish_with_message("done");
@@ -143,6 +209,115 @@ impl Downloader {
         });
         inner.await
     }
+    /// HEAD 请求获取文件信息，失败、超时或不支持时返回 None
+    async fn head(&self, uri: &str, limit: Duration) -> Option<Response> {
+        match timeout(
+            limit,
+            self.client
+                .request(with_host_headers(Request::head(uri.to_string()), uri)),
+        )
+        .await
+        {
+            Ok(Ok(head)) if head.status().is_successful() => Some(head),
+            Ok(Ok(head)) => {
+                debug!("HEAD returned {}", head.status().code());
+                None
+            }
+            Ok(Err(e)) => {
+                debug!("HEAD failed: {e}");
+                None
+            }
+            Err(_) => {
+                warn!("HEAD request timed out, falling back to single-threaded download");
+                None
+            }
+        }
+    }
+    /// 多线程分片下载
+    async fn download_ranges(
+        &self,
+        uri: Cow<'static, str>,
+        file: tokio::fs::File,
+        total_size: u64,
+    ) -> Result<()> {
+        // 设置进度条，速度和剩余时间由 report_progress 计算
+        let pb = ProgressBar::new(total_size);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("[{bar:40}] {binary_bytes}/{binary_total_bytes} {msg}")
+                .unwrap()
+                .progress_chars("=>-"),
+        );
+        // 计算分片
+        let split_ranges = (0..total_size).step_by(BLOCK_SIZE as usize).map(|start| {
+            let end = (start + BLOCK_SIZE - 1).min(total_size - 1);
+            Ok((start, end))
+        });
+        file.set_len(total_size).await?;
+        let file = Arc::new(Mutex::new(file));
+        // 已完成的字节数，由各分片共同更新
+        let downloaded = Arc::new(AtomicU64::new(0));
+        let max_attempts = GLOBAL_CONFIG.download.max_attempts();
+        // 并发下载
+        let work =
+            stream::iter(split_ranges).try_for_each_concurrent(CONCURRENCY, |(start, end)| {
+                let file = file.clone();
+                let uri = uri.clone();
+                let downloaded = downloaded.clone();
+                async move {
+                    let mut attempt = 1;
+                    loop {
+                        match download_chunk(&self.client, uri.clone(), &file, start, end).await {
+                            Ok(_) => break,
+                            Err(e) if attempt < max_attempts && is_transient(&e) => {
+                                error!("chunk error: {e:?}");
+                                tokio::time::sleep(backoff(attempt)).await;
+                                attempt += 1;
+                            }
+                            Err(e) => return Err(e),
+                        }
+                    }
+                    downloaded.fetch_add(end - start + 1, Ordering::Relaxed);
+                    Ok(())
+                }
+            });
+        let result = select! {
+            result = work => result,
+            _ = report_progress(&pb, &downloaded, total_size) => unreachable!(),
+        };
+        if let Err(e) = result {
+            pb.abandon();
+            return Err(e);
+        }
+        file.lock().await.flush().await?;
+        let downloaded = downloaded.load(Ordering::Relaxed);
+        if downloaded != total_size {
+            return Err(anyhow!(
+                "Download incomplete: {downloaded} of {total_size} bytes"
+            ));
+        }
+        pb.set_position(downloaded);
+        pb.finish_with_message("done");
+        Ok(())
+    }
+    /// 下载并按给定的摘要校验，成功时打印文件的摘要
+    pub async fn download_verified(
+        &self,
+        uri: impl Into<Cow<'static, str>>,
+        expected: Option<&Checksum>,
+    ) -> Result<PathBuf> {
+        let path = self.download(uri).await?;
+        let digests = file_digests(&path).await?;
+        if let Some(expected) = expected
+            && let Err(e) = expected.check(&digests)
+        {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e.context("File verification error"));
+        }
+        info!("sha1:   {}", digests.sha1);
+        info!("sha256: {}", digests.sha256);
+        Ok(path)
+    }
     /// 下载并校验 sha256
     pub async fn download_with_sha256(
         &self,
@@ -172,6 +347,130 @@ impl Downloader {
     }
 }
 
+/// 服务端没有按 Range 返回分片
+#[derive(Debug)]
+struct RangeIgnored;
+
+impl std::fmt::Display for RangeIgnored {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The server ignored the range request")
+    }
+}
+
+impl std::error::Error for RangeIgnored {}
+
+/// 按全局配置为请求附加该主机的请求头
+pub fn with_host_headers(
+    mut request: nyquest::r#async::Request,
+    uri: &str,
+) -> nyquest::r#async::Request {
+    // 形如 scheme://user@host:port/path
+    let host = uri
+        .split_once("://")
+        .map_or(uri, |(_, rest)| rest)
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = host.rsplit_once('@').map_or(host, |(_, h)| h);
+    let host = host.split(':').next().unwrap_or_default();
+    for (key, value) in GLOBAL_CONFIG.download.headers_for(host) {
+        request = request.with_header(key.clone(), value.clone());
+    }
+    request
+}
+
+/// 是否处于离线模式
+pub fn is_offline() -> bool {
+    OFFLINE.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// 将下载的文件移动到目标位置
+/// 缓存目录与目标不在同一文件系统时无法重命名（例如 Docker 卷），改为复制后删除
+pub async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            debug!("{} is on another filesystem, copying", from.display());
+            copy_and_remove(from, to).await
+        }
+        r => Ok(r?),
+    }
+}
+
+/// 跨文件系统移动文件
+async fn copy_and_remove(from: &Path, to: &Path) -> Result<()> {
+    // 先复制到目标旁的临时文件，避免中断时留下不完整的文件
+    let mut part = to.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+    if let Err(e) = tokio::fs::copy(from, &part).await {
+        let _ = tokio::fs::remove_file(&part).await;
+        return Err(e.into());
+    }
+    tokio::fs::rename(&part, to).await?;
+    tokio::fs::remove_file(from).await?;
+    Ok(())
+}
+
+/// 指数退避，附加最多一半的随机抖动，避免多个分片同时重试
+fn backoff(attempt: usize) -> Duration {
+    let base = GLOBAL_CONFIG
+        .download
+        .retry_delay_ms
+        .saturating_mul(1 << (attempt - 1).min(16));
+    // 每个 RandomState 使用不同的随机密钥
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(base.saturating_add(random % (base / 2 + 1)))
+}
+
+/// 超时、限流和服务端错误可以通过重试解决
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || matches!(status.code(), 408 | 429)
+}
+
+/// 下载错误是否值得重试，404、403 等客户端错误重试也不会成功
+fn is_transient(e: &anyhow::Error) -> bool {
+    // 重试无法解决服务端忽略分片的问题
+    if e.is::<RangeIgnored>() {
+        return false;
+    }
+    match e.downcast_ref::<nyquest::Error>() {
+        Some(nyquest::Error::NonSuccessfulStatusCode(status)) => is_transient_status(*status),
+        Some(nyquest::Error::InvalidUrl) => false,
+        _ => true,
+    }
+}
+
+/// 按已下载字节数刷新进度条，用指数移动平均平滑速度和剩余时间
+async fn report_progress(pb: &ProgressBar, downloaded: &AtomicU64, total_size: u64) {
+    /// 新采样的权重
+    const ALPHA: f64 = 0.3;
+    let mut interval = tokio::time::interval(Duration::from_millis(250));
+    let mut last = (Instant::now(), 0u64);
+    let mut speed: Option<f64> = None;
+    loop {
+        interval.tick().await;
+        let now = (Instant::now(), downloaded.load(Ordering::Relaxed));
+        let elapsed = now.0.duration_since(last.0).as_secs_f64();
+        if elapsed > 0.0 {
+            let sample = (now.1 - last.1) as f64 / elapsed;
+            speed = Some(match speed {
+                None => sample,
+                Some(s) => ALPHA * sample + (1.0 - ALPHA) * s,
+            });
+        }
+        last = now;
+        pb.set_position(now.1);
+        if let Some(speed) = speed.filter(|s| *s > 0.0) {
+            let eta = Duration::from_secs_f64((total_size - now.1) as f64 / speed);
+            pb.set_message(format!(
+                "{}/s ({})",
+                HumanBytes(speed as u64),
+                HumanDuration(eta)
+            ));
+        }
+    }
+}
+
 /// 下载分片
 async fn download_chunk(
     client: &AsyncClient,
@@ -181,12 +480,175 @@ async fn download_chunk(
     end: u64,
 ) -> Result<()> {
     let range_header = format!("bytes={}-{}", start, end);
-    let resp = client
-        .request(Request::get(uri).with_header("Range", range_header))
-        .await?;
+    let resp = timeout(
+        GLOBAL_CONFIG.download.timeout(),
+        client.request(with_host_headers(
+            Request::get(uri.clone()).with_header("Range", range_header),
+            &uri,
+        )),
+    )
+    .await
+    .map_err(|_| anyhow!("Timed out waiting for {uri}"))??
+    .with_successful_status()?;
+    // 只有 206 才是分片内容，200 表示服务端忽略了 Range
+    if resp.status().code() != 206 {
+        return Err(RangeIgnored.into());
+    }
     let bytes = resp.bytes().await?;
+    if bytes.len() as u64 != end - start + 1 {
+        return Err(RangeIgnored.into());
+    }
     let mut file = file.lock().await;
     file.seek(tokio::io::SeekFrom::Start(start)).await?;
     file.write_all(&bytes).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::{http_response as response, http_server, temp_dir};
+
+    /// 返回 None 时保持连接但不响应
+    type Handler = fn(&str, &[u8]) -> Option<Vec<u8>>;
+
+    /// 本地模拟的 HTTP 服务端，返回文件地址
+    async fn mock_server(body: Vec<u8>, handler: Handler) -> String {
+        let body: &'static [u8] = body.leak();
+        let addr = http_server(Box::new(move |request| handler(request, body))).await;
+        format!("{addr}/server.jar")
+    }
+
+    /// 声明支持分片，但对 Range 请求总是返回完整内容
+    fn ignore_range(request: &str, body: &[u8]) -> Option<Vec<u8>> {
+        let headers = "Accept-Ranges: bytes\r\n";
+        Some(if request.starts_with("HEAD") {
+            let mut head = response("200 OK", headers, body);
+            head.truncate(head.len() - body.len());
+            head
+        } else {
+            response("200 OK", headers, body)
+        })
+    }
+
+    fn test_body() -> Vec<u8> {
+        (0..3 * BLOCK_SIZE + 123).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn ignored_range_is_detected() {
+        let body = test_body();
+        let uri = mock_server(body, ignore_range).await;
+        let downloader = Downloader::new().await;
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let file = Arc::new(Mutex::new(tokio::fs::File::create(&path).await.unwrap()));
+        let e = download_chunk(&downloader.client, uri.into(), &file, 0, 99)
+            .await
+            .unwrap_err();
+        assert!(e.is::<RangeIgnored>());
+        assert!(!is_transient(&e));
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn ignored_range_falls_back_to_single_thread() {
+        let body = test_body();
+        let uri = mock_server(body.clone(), ignore_range).await;
+        let path = Downloader::new().await.download(uri).await.unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), body);
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    /// HEAD 请求不响应，GET 正常返回
+    fn stall_head(request: &str, body: &[u8]) -> Option<Vec<u8>> {
+        if request.starts_with("HEAD") {
+            None
+        } else {
+            Some(response("200 OK", "", body))
+        }
+    }
+
+    /// 不支持 HEAD 请求
+    fn reject_head(request: &str, body: &[u8]) -> Option<Vec<u8>> {
+        Some(if request.starts_with("HEAD") {
+            response("405 Method Not Allowed", "", b"")
+        } else {
+            response("200 OK", "", body)
+        })
+    }
+
+    #[tokio::test]
+    async fn stalled_head_times_out() {
+        let uri = mock_server(test_body(), stall_head).await;
+        let limit = Duration::from_millis(200);
+        let start = Instant::now();
+        let head = Downloader::new().await.head(&uri, limit).await;
+        assert!(head.is_none());
+        assert!(start.elapsed() < limit * 10);
+    }
+
+    #[tokio::test]
+    async fn unsupported_head_falls_back_to_single_thread() {
+        let body = test_body();
+        let uri = mock_server(body.clone(), reject_head).await;
+        let path = Downloader::new().await.download(uri).await.unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), body);
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    /// 分块传输，不提供 Content-Length
+    fn chunked(request: &str, body: &[u8]) -> Option<Vec<u8>> {
+        let mut response = b"HTTP/1.1 200 OK\r\nAccept-Ranges: bytes\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n".to_vec();
+        if request.starts_with("HEAD") {
+            return Some(response);
+        }
+        for chunk in body.chunks(BLOCK_SIZE as usize) {
+            response.extend(format!("{:x}\r\n", chunk.len()).into_bytes());
+            response.extend_from_slice(chunk);
+            response.extend(b"\r\n");
+        }
+        response.extend(b"0\r\n\r\n");
+        Some(response)
+    }
+
+    #[tokio::test]
+    async fn missing_content_length_falls_back_to_single_thread() {
+        let body = test_body();
+        let uri = mock_server(body.clone(), chunked).await;
+        let path = Downloader::new().await.download(uri).await.unwrap();
+        assert_eq!(tokio::fs::read(&path).await.unwrap(), body);
+        let _ = tokio::fs::remove_file(&path).await;
+    }
+
+    #[tokio::test]
+    async fn move_file_replaces_the_destination() {
+        let dir = temp_dir();
+        let (from, to) = (dir.join("download"), dir.join("server.jar"));
+        std::fs::write(&from, "new").unwrap();
+        std::fs::write(&to, "old").unwrap();
+        move_file(&from, &to).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// rename 返回 EXDEV 时走的路径
+    #[tokio::test]
+    async fn cross_device_fallback_copies_then_removes() {
+        let dir = temp_dir();
+        let (from, to) = (dir.join("download"), dir.join("server.jar"));
+        std::fs::write(&from, "new").unwrap();
+        std::fs::write(&to, "old").unwrap();
+        copy_and_remove(&from, &to).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+        assert!(!from.exists());
+        assert!(!dir.join("server.jar.part").exists());
+
+        // 复制失败时不留下临时文件，也不影响原有的目标
+        let missing = dir.join("missing");
+        assert!(copy_and_remove(&missing, &to).await.is_err());
+        assert_eq!(std::fs::read_to_string(&to).unwrap(), "new");
+        assert!(!dir.join("server.jar.part").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
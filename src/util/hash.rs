@@ -26,3 +26,135 @@ impl From<[u8; 32]> for Sha256Digest {
         Sha256Digest(value)
     }
 }
+
+/// 文件摘要，十六进制字符串
+pub struct FileDigests {
+    pub sha1: String,
+    pub sha256: String,
+}
+
+/// 一次读取同时计算文件的 sha1 和 sha256
+pub async fn file_digests(path: &std::path::Path) -> anyhow::Result<FileDigests> {
+    use sha2::Digest;
+    use tokio::io::AsyncReadExt;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut sha1 = sha1::Sha1::new();
+    let mut sha256 = sha2::Sha256::new();
+    let mut buffer = vec![0u8; 8192].into_boxed_slice();
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        sha1.update(&buffer[..n]);
+        sha256.update(&buffer[..n]);
+    }
+    Ok(FileDigests {
+        sha1: hex::encode(sha1.finalize()),
+        sha256: hex::encode(sha256.finalize()),
+    })
+}
+
+/// 计算文件的 sha1，返回十六进制字符串
+pub async fn file_sha1(path: &std::path::Path) -> anyhow::Result<String> {
+    Ok(file_digests(path).await?.sha1)
+}
+
+/// 期望的文件摘要
+#[derive(Clone, Debug)]
+pub enum Checksum {
+    Sha1(String),
+    Sha256(String),
+}
+
+/// 按长度识别算法，也可以使用 `sha1:` `sha256:` 前缀
+impl std::str::FromStr for Checksum {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let (algorithm, hex) = match s.split_once(':') {
+            Some((a, h)) => (Some(a.to_string()), h.to_string()),
+            None => (None, s),
+        };
+        if hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("Checksum must be a hexadecimal string"));
+        }
+        match (algorithm.as_deref(), hex.len()) {
+            (None | Some("sha1"), 40) => Ok(Checksum::Sha1(hex)),
+            (None | Some("sha256"), 64) => Ok(Checksum::Sha256(hex)),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported checksum, expected a sha1 (40) or sha256 (64) hex digest"
+            )),
+        }
+    }
+}
+
+impl Checksum {
+    /// 与文件摘要比较，不一致时返回错误
+    pub fn check(&self, digests: &FileDigests) -> anyhow::Result<()> {
+        let (name, expected, actual) = match self {
+            Checksum::Sha1(e) => ("sha1", e, &digests.sha1),
+            Checksum::Sha256(e) => ("sha256", e, &digests.sha256),
+        };
+        if expected.eq_ignore_ascii_case(actual) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "{name} mismatch: expected {expected}, got {actual}"
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::temp_dir;
+
+    const SHA1: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+    const SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn checksum_from_str() {
+        assert!(matches!(SHA1.parse(), Ok(Checksum::Sha1(h)) if h == SHA1));
+        assert!(matches!(SHA256.parse(), Ok(Checksum::Sha256(h)) if h == SHA256));
+        let prefixed = format!(" SHA1:{} ", SHA1.to_uppercase());
+        assert!(matches!(prefixed.parse(), Ok(Checksum::Sha1(h)) if h == SHA1));
+        assert!(matches!(
+            format!("sha256:{SHA256}").parse(),
+            Ok(Checksum::Sha256(_))
+        ));
+    }
+
+    #[test]
+    fn checksum_rejects_invalid() {
+        for s in [
+            "",
+            "sha1:",
+            &SHA1[1..],
+            &format!("{}g", &SHA1[1..]),
+            &format!("sha256:{SHA1}"),
+            &format!("md5:{SHA1}"),
+        ] {
+            assert!(s.parse::<Checksum>().is_err(), "{s}");
+        }
+    }
+
+    #[tokio::test]
+    async fn checksum_checks_file_digests() {
+        let dir = temp_dir();
+        let file = dir.join("abc");
+        tokio::fs::write(&file, "abc").await.unwrap();
+        let digests = file_digests(&file).await.unwrap();
+        assert_eq!(digests.sha1, SHA1);
+        assert_eq!(digests.sha256, SHA256);
+
+        assert!(SHA1.parse::<Checksum>().unwrap().check(&digests).is_ok());
+        assert!(SHA256.parse::<Checksum>().unwrap().check(&digests).is_ok());
+        let wrong = SHA1.replace('a', "b").parse::<Checksum>().unwrap();
+        assert!(wrong.check(&digests).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,8 +1,83 @@
+use std::borrow::Cow;
+use std::io::IsTerminal;
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tailspin::Highlighter;
 use tailspin::config::*;
 use tailspin::style::{Color, Style};
 
+/// 是否输出颜色
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// 根据 NO_COLOR、CLICOLOR_FORCE 和标准输出是否为终端决定是否输出颜色
+pub fn setup_color() -> bool {
+    let is_set = |key: &str| std::env::var_os(key).is_some_and(|v| !v.is_empty() && v != "0");
+    let color = if is_set("CLICOLOR_FORCE") {
+        true
+    } else {
+        !is_set("NO_COLOR") && std::io::stdout().is_terminal()
+    };
+    COLOR.store(color, Ordering::Relaxed);
+    colored::control::set_override(color);
+    color
+}
+
+/// 高亮一行输出，禁用颜色时只去除格式代码
+/// 服务端自带的 ANSI 和 § 格式代码会先被去除，避免与高亮重叠
+pub fn highlight(line: &str) -> Cow<'_, str> {
+    let line = strip_codes(line);
+    if !COLOR.load(Ordering::Relaxed) {
+        return line;
+    }
+    match HIGHLIGHTER.apply(&line) {
+        Cow::Borrowed(_) => line,
+        Cow::Owned(s) => Cow::Owned(s),
+    }
+}
+
+/// 去除 ANSI 转义序列和 Minecraft 的 § 格式代码
+fn strip_codes(line: &str) -> Cow<'_, str> {
+    if !line.contains(['\x1b', '§']) {
+        return Cow::Borrowed(line);
+    }
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            // § 后跟一个代码字符，RGB 颜色 §x§r§r§g§g§b§b 也由多个代码组成
+            '§' => {
+                chars.next();
+            }
+            '\x1b' => match chars.next() {
+                // CSI：参数后以 0x40..=0x7e 结束
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC：以 BEL 或 ESC \ 结束
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                // 其余转义序列只有一个字符
+                _ => {}
+            },
+            c => result.push(c),
+        }
+    }
+    Cow::Owned(result)
+}
+
 pub static HIGHLIGHTER: LazyLock<Highlighter> = LazyLock::new(|| {
     let mut builder = Highlighter::builder();
 
@@ -189,3 +264,33 @@ pub static HIGHLIGHTER: LazyLock<Highlighter> = LazyLock::new(|| {
 
     builder.build().expect("Failed to build highlighter")
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_codes_keeps_plain_lines() {
+        assert!(matches!(
+            strip_codes("[Server thread/INFO]: Done"),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn strip_codes_removes_formatting() {
+        for (line, plain) in [
+            ("§aGreen §lbold§r", "Green bold"),
+            ("§x§f§f§0§0§0§0RGB", "RGB"),
+            ("\x1b[0;32;1mINFO\x1b[m done", "INFO done"),
+            ("\x1b]0;title\x07text", "text"),
+            ("\x1b]8;;https://a\x1b\\link\x1b]8;;\x1b\\", "link"),
+            ("\x1b7saved\x1b8", "saved"),
+            // 末尾不完整的代码
+            ("trailing§", "trailing"),
+            ("trailing\x1b[31", "trailing"),
+        ] {
+            assert_eq!(strip_codes(line), plain, "{line:?}");
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::{Result, anyhow};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// 基于 PID 的锁文件，离开作用域时释放
+pub struct PidLock {
+    path: PathBuf,
+}
+
+impl PidLock {
+    /// 获取锁，已被存活的进程持有时返回错误，失效的锁会被清除
+    pub fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        for _ in 0..2 {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+            {
+                Ok(mut file) => {
+                    write!(file, "{}", std::process::id())?;
+                    debug!("Lock acquired: {}", path.display());
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let pid = std::fs::read_to_string(path)?.trim().parse::<u32>().ok();
+                    match pid {
+                        Some(pid) if is_alive(pid) => {
+                            return Err(Locked {
+                                pid,
+                                path: path.to_path_buf(),
+                            }
+                            .into());
+                        }
+                        _ => {
+                            warn!("Removing stale lock file: {}", path.display());
+                            std::fs::remove_file(path)?;
+                        }
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(anyhow!("Failed to acquire lock: {}", path.display()))
+    }
+}
+
+/// 锁已被其他存活的进程持有
+#[derive(Debug)]
+pub struct Locked {
+    pid: u32,
+    path: PathBuf,
+}
+
+impl std::fmt::Display for Locked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Already running in process {}, lock file: {}",
+            self.pid,
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for Locked {}
+
+impl Drop for PidLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 检查进程是否存活
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // SAFETY: 信号 0 只检查进程是否存在，不会发送信号
+    let ret = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    ret == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// 检查进程是否存活
+#[cfg(windows)]
+fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).contains(&pid.to_string()))
+        // 无法判断时视为存活，避免误删
+        .unwrap_or(true)
+}
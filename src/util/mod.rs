@@ -1,3 +1,11 @@
+pub mod channel;
 pub mod downloader;
 pub mod hash;
 pub mod highlighter;
+pub mod lock;
+pub mod nbt;
+pub mod output;
+pub mod properties;
+pub mod slp;
+#[cfg(test)]
+pub mod testing;
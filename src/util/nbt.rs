@@ -0,0 +1,321 @@
+// 只读的 NBT 解析，用于读取 level.dat
+
+use anyhow::{Result, anyhow};
+use flate2::read::GzDecoder;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+
+/// 嵌套层数上限，防止异常数据导致栈溢出
+const MAX_DEPTH: usize = 512;
+
+/// 解析后的标签，只保留读取 level.dat 需要的类型
+pub enum Tag {
+    /// Byte Short Int Long
+    Int(i64),
+    String(String),
+    Compound(HashMap<String, Tag>),
+    /// 浮点数、数组和列表只跳过
+    Other,
+}
+
+impl Tag {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// level.dat 中的世界信息
+pub struct LevelInfo {
+    /// 世界的数据版本，1.9 之前的世界没有
+    pub data_version: Option<i32>,
+    pub level_name: Option<String>,
+    /// 默认游戏模式
+    pub game_type: Option<GameType>,
+    pub seed: Option<i64>,
+}
+
+#[derive(Clone, Copy)]
+pub enum GameType {
+    Survival,
+    Creative,
+    Adventure,
+    Spectator,
+}
+
+impl GameType {
+    fn from_id(id: i64) -> Option<Self> {
+        match id {
+            0 => Some(GameType::Survival),
+            1 => Some(GameType::Creative),
+            2 => Some(GameType::Adventure),
+            3 => Some(GameType::Spectator),
+            _ => None,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            GameType::Survival => "survival",
+            GameType::Creative => "creative",
+            GameType::Adventure => "adventure",
+            GameType::Spectator => "spectator",
+        }
+    }
+}
+
+/// 读取 gzip 压缩的 level.dat，缺少的字段为 None
+pub async fn read_level_dat(path: &Path) -> Result<LevelInfo> {
+    let root = read(&tokio::fs::read(path).await?)?;
+    let data = root
+        .get("Data")
+        .ok_or_else(|| anyhow!("{} has no Data tag", path.display()))?;
+    Ok(LevelInfo {
+        data_version: data
+            .get("DataVersion")
+            .and_then(Tag::as_i64)
+            .and_then(|v| i32::try_from(v).ok()),
+        level_name: data
+            .get("LevelName")
+            .and_then(Tag::as_str)
+            .map(str::to_string),
+        game_type: data
+            .get("GameType")
+            .and_then(Tag::as_i64)
+            .and_then(GameType::from_id),
+        // 1.16 起种子位于 WorldGenSettings
+        seed: data
+            .get("WorldGenSettings")
+            .and_then(|s| s.get("seed"))
+            .or_else(|| data.get("RandomSeed"))
+            .and_then(Tag::as_i64),
+    })
+}
+
+/// 解析 NBT 数据，自动识别 gzip 压缩，返回根标签
+pub fn read(data: &[u8]) -> Result<Tag> {
+    let mut buf = Vec::new();
+    let data = if data.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(data).read_to_end(&mut buf)?;
+        buf.as_slice()
+    } else {
+        data
+    };
+    let mut reader = Reader { data };
+    let id = reader.u8()?;
+    if id != 10 {
+        return Err(anyhow!("The root tag is not a compound"));
+    }
+    // 根标签的名称通常为空
+    reader.string()?;
+    reader.payload(id, 0)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let (head, rest) = self
+            .data
+            .split_first_chunk::<N>()
+            .ok_or_else(|| anyhow!("Unexpected end of NBT data"))?;
+        self.data = rest;
+        Ok(*head)
+    }
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+    fn len(&mut self) -> Result<usize> {
+        let len = i32::from_be_bytes(self.take()?);
+        // 每个元素至少一个字节，长度不可能超过剩余数据
+        usize::try_from(len)
+            .ok()
+            .filter(|&l| l <= self.data.len())
+            .ok_or_else(|| anyhow!("Invalid NBT length {len}"))
+    }
+    /// 跳过 count 个 size 字节的元素
+    fn skip(&mut self, size: usize, count: usize) -> Result<Tag> {
+        self.data = size
+            .checked_mul(count)
+            .and_then(|n| self.data.get(n..))
+            .ok_or_else(|| anyhow!("Unexpected end of NBT data"))?;
+        Ok(Tag::Other)
+    }
+    fn string(&mut self) -> Result<String> {
+        let len = u16::from_be_bytes(self.take()?) as usize;
+        let bytes = self
+            .data
+            .get(..len)
+            .ok_or_else(|| anyhow!("Unexpected end of NBT data"))?;
+        self.data = &self.data[len..];
+        // Java 的 Modified UTF-8 与 UTF-8 仅在少数字符上不同
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+    fn payload(&mut self, id: u8, depth: usize) -> Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("NBT data is nested too deeply"));
+        }
+        Ok(match id {
+            1 => Tag::Int(i8::from_be_bytes(self.take()?) as i64),
+            2 => Tag::Int(i16::from_be_bytes(self.take()?) as i64),
+            3 => Tag::Int(i32::from_be_bytes(self.take()?) as i64),
+            4 => Tag::Int(i64::from_be_bytes(self.take()?)),
+            5 => self.skip(4, 1)?,
+            6 => self.skip(8, 1)?,
+            7 => {
+                let len = self.len()?;
+                self.skip(1, len)?
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item = self.u8()?;
+                let len = self.len()?;
+                for _ in 0..len {
+                    self.payload(item, depth + 1)?;
+                }
+                Tag::Other
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let id = self.u8()?;
+                    if id == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(id, depth + 1)?);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let len = self.len()?;
+                self.skip(4, len)?
+            }
+            12 => {
+                let len = self.len()?;
+                self.skip(8, len)?
+            }
+            _ => return Err(anyhow!("Unknown NBT tag type {id}")),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::temp_dir;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    /// 写入带名称的标签头
+    fn named(buf: &mut Vec<u8>, id: u8, name: &str) {
+        buf.push(id);
+        buf.extend((name.len() as u16).to_be_bytes());
+        buf.extend(name.as_bytes());
+    }
+
+    fn level_dat() -> Vec<u8> {
+        let mut buf = Vec::new();
+        named(&mut buf, 10, "");
+        named(&mut buf, 10, "Data");
+        named(&mut buf, 3, "DataVersion");
+        buf.extend(3953i32.to_be_bytes());
+        named(&mut buf, 8, "LevelName");
+        buf.extend(5u16.to_be_bytes());
+        buf.extend(b"world");
+        named(&mut buf, 3, "GameType");
+        buf.extend(1i32.to_be_bytes());
+        // 需要跳过的类型
+        named(&mut buf, 6, "BorderSize");
+        buf.extend(6.0e7f64.to_be_bytes());
+        named(&mut buf, 9, "ServerBrands");
+        buf.push(8);
+        buf.extend(1i32.to_be_bytes());
+        buf.extend(7u16.to_be_bytes());
+        buf.extend(b"vanilla");
+        named(&mut buf, 11, "Ints");
+        buf.extend(2i32.to_be_bytes());
+        buf.extend([0; 8]);
+        named(&mut buf, 10, "WorldGenSettings");
+        named(&mut buf, 4, "seed");
+        buf.extend((-42i64).to_be_bytes());
+        buf.push(0);
+        named(&mut buf, 1, "hardcore");
+        buf.push(1);
+        buf.push(0);
+        buf.push(0);
+        buf
+    }
+
+    #[test]
+    fn read_compound() {
+        let root = read(&level_dat()).unwrap();
+        let data = root.get("Data").unwrap();
+        assert_eq!(data.get("DataVersion").and_then(Tag::as_i64), Some(3953));
+        assert_eq!(data.get("LevelName").and_then(Tag::as_str), Some("world"));
+        assert!(matches!(data.get("BorderSize"), Some(Tag::Other)));
+        assert!(matches!(data.get("ServerBrands"), Some(Tag::Other)));
+        assert_eq!(data.get("hardcore").and_then(Tag::as_i64), Some(1));
+        let seed = data.get("WorldGenSettings").and_then(|s| s.get("seed"));
+        assert_eq!(seed.and_then(Tag::as_i64), Some(-42));
+    }
+
+    #[test]
+    fn read_rejects_invalid_data() {
+        let data = level_dat();
+        assert!(read(&data[..data.len() - 1]).is_err());
+        assert!(read(&data[..10]).is_err());
+        assert!(read(&[]).is_err());
+        // 根标签不是 Compound
+        assert!(read(&[8, 0, 0, 0, 0]).is_err());
+        // 未知的标签类型
+        assert!(read(&[10, 0, 0, 13, 0, 0]).is_err());
+        // 长度超过剩余数据
+        assert!(read(&[10, 0, 0, 7, 0, 0, 0x7f, 0xff, 0xff, 0xff, 0]).is_err());
+    }
+
+    #[test]
+    fn read_rejects_deep_nesting() {
+        let mut data = vec![10, 0, 0];
+        for _ in 0..=MAX_DEPTH {
+            named(&mut data, 10, "");
+        }
+        data.extend(std::iter::repeat_n(0, MAX_DEPTH + 2));
+        assert!(read(&data).is_err());
+    }
+
+    #[tokio::test]
+    async fn read_gzip_level_dat() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&level_dat()).unwrap();
+        let dir = temp_dir();
+        let path = dir.join("level.dat");
+        tokio::fs::write(&path, encoder.finish().unwrap())
+            .await
+            .unwrap();
+
+        let info = read_level_dat(&path).await.unwrap();
+        assert_eq!(info.data_version, Some(3953));
+        assert_eq!(info.level_name.as_deref(), Some("world"));
+        assert_eq!(info.game_type.map(GameType::name), Some("creative"));
+        assert_eq!(info.seed, Some(-42));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+// 面向脚本的 JSON 输出
+// 开启后日志写入 stderr，stdout 只有一行结果，失败时 stderr 的最后一行为错误
+
+use crate::core::mc_server::NotImplemented;
+use crate::util::lock::Locked;
+use serde_json::{Value, json};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+/// 命令是否已输出结果
+static PRINTED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// 输出命令的结果，仅在 JSON 模式下有效
+pub fn data(data: Value) {
+    if is_json() {
+        println!("{}", json!({ "ok": true, "data": data }));
+        PRINTED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 错误类别，决定 JSON 中的 code 和退出码
+/// 退出码 2 由参数解析错误占用
+#[derive(Clone, Copy)]
+enum ErrorKind {
+    General,
+    Config,
+    Network,
+    Io,
+    NotFound,
+    Locked,
+    Unsupported,
+}
+
+impl ErrorKind {
+    /// 按错误链从外到内匹配第一个已知的错误
+    fn of(e: &anyhow::Error) -> Self {
+        for cause in e.chain() {
+            if cause.is::<Locked>() {
+                return ErrorKind::Locked;
+            }
+            if cause.is::<NotImplemented>() {
+                return ErrorKind::Unsupported;
+            }
+            if cause.is::<nyquest::Error>() {
+                return ErrorKind::Network;
+            }
+            if cause.is::<toml::de::Error>() {
+                return ErrorKind::Config;
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                return match e.kind() {
+                    std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+                    _ => ErrorKind::Io,
+                };
+            }
+        }
+        ErrorKind::General
+    }
+    fn code(self) -> &'static str {
+        match self {
+            ErrorKind::General => "error",
+            ErrorKind::Config => "config",
+            ErrorKind::Network => "network",
+            ErrorKind::Io => "io",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Locked => "locked",
+            ErrorKind::Unsupported => "unsupported",
+        }
+    }
+    fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::General => 1,
+            ErrorKind::Config => 3,
+            ErrorKind::Network => 4,
+            ErrorKind::Io => 5,
+            ErrorKind::NotFound => 6,
+            ErrorKind::Locked => 7,
+            ErrorKind::Unsupported => 8,
+        }
+    }
+}
+
+/// 输出命令的最终结果并返回退出码
+pub fn finish(result: anyhow::Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => {
+            if is_json() && !PRINTED.load(Ordering::Relaxed) {
+                println!("{}", json!({ "ok": true }));
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            let kind = ErrorKind::of(&e);
+            if is_json() {
+                let causes: Vec<String> = e.chain().skip(1).map(|c| c.to_string()).collect();
+                eprintln!(
+                    "{}",
+                    json!({
+                        "ok": false,
+                        "error": {
+                            "code": kind.code(),
+                            "message": e.to_string(),
+                            "causes": causes,
+                        },
+                    })
+                );
+            } else {
+                eprintln!("Error: {e:?}");
+            }
+            ExitCode::from(kind.exit_code())
+        }
+    }
+}
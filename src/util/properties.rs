@@ -0,0 +1,157 @@
+// Java properties 格式，用于读取 server.properties
+
+use std::path::PathBuf;
+
+/// 解析 properties 内容，按出现顺序返回键值对
+pub fn parse(content: &str) -> Vec<(String, String)> {
+    let mut result = Vec::new();
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+        // 以奇数个反斜杠结尾的行延续到下一行
+        let mut logical = line.to_string();
+        while logical.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1 {
+            logical.pop();
+            match lines.next() {
+                Some(next) => logical.push_str(next.trim_start()),
+                None => break,
+            }
+        }
+        // 第一个未转义的 '=' ':' 或空白分隔键和值
+        let mut key_end = logical.len();
+        let mut escaped = false;
+        for (i, c) in logical.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '=' || c == ':' || c.is_whitespace() {
+                key_end = i;
+                break;
+            }
+        }
+        let key = &logical[..key_end];
+        let rest = logical[key_end..].trim_start();
+        let value = rest
+            .strip_prefix(['=', ':'])
+            .map_or(rest, |v| v.trim_start());
+        result.push((unescape(key), unescape(value)));
+    }
+    result
+}
+
+/// 获取指定键的值
+pub fn get<'a>(properties: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    properties
+        .iter()
+        .rev()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// 世界目录的名称，未设置时为 world
+pub fn level_name(properties: &[(String, String)]) -> &str {
+    get(properties, "level-name")
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .unwrap_or("world")
+}
+
+/// 世界的全部目录，Bukkit 系服务端将下界和末地保存在单独的目录
+pub fn world_dirs(properties: &[(String, String)]) -> Vec<PathBuf> {
+    let level = level_name(properties);
+    [
+        level.to_string(),
+        format!("{level}_nether"),
+        format!("{level}_the_end"),
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .collect()
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('u') => {
+                let code: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    Some(c) => result.push(c),
+                    None => {
+                        result.push_str("\\u");
+                        result.push_str(&code);
+                    }
+                }
+            }
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_separators_and_comments() {
+        let content = "# comment\n! also comment\n\nlevel-name=survival\nmotd : A Server\nspaces  value here\nempty=\n  indented=yes\n";
+        let properties = parse(content);
+        assert_eq!(
+            properties,
+            [
+                ("level-name", "survival"),
+                ("motd", "A Server"),
+                ("spaces", "value here"),
+                ("empty", ""),
+                ("indented", "yes"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_escapes_and_continuations() {
+        let properties = parse(
+            "a\\=b=c\\:d\nmotd=\\u00a7aHi\\nthere\nlong=one \\\n    two\nslash=end\\\\\nnext=1\n",
+        );
+        assert_eq!(get(&properties, "a=b"), Some("c:d"));
+        assert_eq!(get(&properties, "motd"), Some("\u{a7}aHi\nthere"));
+        assert_eq!(get(&properties, "long"), Some("one two"));
+        // 偶数个反斜杠不延续
+        assert_eq!(get(&properties, "slash"), Some("end\\"));
+        assert_eq!(get(&properties, "next"), Some("1"));
+    }
+
+    #[test]
+    fn get_returns_the_last_value() {
+        let properties = parse("level-name=a\nlevel-name=b\n");
+        assert_eq!(get(&properties, "level-name"), Some("b"));
+        assert_eq!(get(&properties, "missing"), None);
+    }
+
+    #[test]
+    fn level_name_and_world_dirs() {
+        assert_eq!(level_name(&parse("")), "world");
+        assert_eq!(level_name(&parse("level-name=  \n")), "world");
+        let properties = parse("level-name=survival\n");
+        assert_eq!(level_name(&properties), "survival");
+        assert_eq!(
+            world_dirs(&properties),
+            ["survival", "survival_nether", "survival_the_end"].map(PathBuf::from)
+        );
+    }
+}
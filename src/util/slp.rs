@@ -0,0 +1,262 @@
+// Java 版的 Server List Ping 协议，用于确认服务端能正常响应
+
+use anyhow::{Result, anyhow};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// 响应的最大长度，防止异常数据占用过多内存
+const MAX_PACKET: usize = 1024 * 1024;
+
+/// 服务端状态
+pub struct Status {
+    /// 版本名称，例如 "1.21.1" 或 "Paper 1.21.1"
+    pub version: String,
+    pub protocol: i32,
+    pub online: u32,
+    pub max: u32,
+    /// 去除格式代码后的 MOTD
+    pub motd: String,
+    pub latency: Duration,
+}
+
+/// 查询服务端状态
+pub async fn ping(host: &str, port: u16, limit: Duration) -> Result<Status> {
+    timeout(limit, ping_inner(host, port))
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for {host}:{port}"))?
+}
+
+async fn ping_inner(host: &str, port: u16) -> Result<Status> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+
+    // 握手，协议版本 -1 表示仅查询状态，下一状态 1 为 Status
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, 0x00);
+    write_varint(&mut handshake, -1);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+    send_packet(&mut stream, &handshake).await?;
+    // 状态请求
+    send_packet(&mut stream, &[0x00]).await?;
+
+    let response = read_packet(&mut stream).await?;
+    let mut cursor = response.as_slice();
+    if read_varint(&mut cursor)? != 0x00 {
+        return Err(anyhow!("Unexpected status response"));
+    }
+    let length = read_varint(&mut cursor)? as usize;
+    let json = cursor
+        .get(..length)
+        .ok_or_else(|| anyhow!("Truncated status response"))?;
+    let json: serde_json::Value = serde_json::from_slice(json)?;
+
+    // Ping，测量往返延迟
+    let start = Instant::now();
+    let mut ping = vec![0x01];
+    ping.extend_from_slice(&0i64.to_be_bytes());
+    send_packet(&mut stream, &ping).await?;
+    let _ = read_packet(&mut stream).await?;
+    let latency = start.elapsed();
+
+    let count = |key: &str| {
+        json["players"][key]
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .unwrap_or_default()
+    };
+    Ok(Status {
+        version: json["version"]["name"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        protocol: json["version"]["protocol"]
+            .as_i64()
+            .and_then(|n| i32::try_from(n).ok())
+            .unwrap_or_default(),
+        online: count("online"),
+        max: count("max"),
+        motd: strip_formatting(&chat_text(&json["description"])),
+        latency,
+    })
+}
+
+async fn send_packet(stream: &mut TcpStream, data: &[u8]) -> Result<()> {
+    let mut packet = Vec::with_capacity(data.len() + 5);
+    write_varint(&mut packet, data.len() as i32);
+    packet.extend_from_slice(data);
+    stream.write_all(&packet).await?;
+    Ok(())
+}
+
+async fn read_packet(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    // 长度前缀最多 5 字节
+    let mut length: u32 = 0;
+    for i in 0..5 {
+        let byte = stream.read_u8().await?;
+        length |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            let length = length as usize;
+            if length > MAX_PACKET {
+                return Err(anyhow!("Status response is too large"));
+            }
+            let mut data = vec![0; length];
+            stream.read_exact(&mut data).await?;
+            return Ok(data);
+        }
+    }
+    Err(anyhow!("VarInt is too long"))
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn read_varint(buf: &mut &[u8]) -> Result<i32> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let (&byte, rest) = buf
+            .split_first()
+            .ok_or_else(|| anyhow!("Truncated VarInt"))?;
+        *buf = rest;
+        value |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(anyhow!("VarInt is too long"))
+}
+
+fn write_string(buf: &mut Vec<u8>, s: &str) {
+    write_varint(buf, s.len() as i32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+/// 聊天组件转为纯文本，旧版本的描述直接是字符串
+fn chat_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(a) => a.iter().map(chat_text).collect(),
+        serde_json::Value::Object(o) => {
+            let mut text = o
+                .get("text")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default()
+                .to_string();
+            if let Some(extra) = o.get("extra") {
+                text.push_str(&chat_text(extra));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+/// 去除 § 开头的格式代码
+fn strip_formatting(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const VARINTS: &[(i32, &[u8])] = &[
+        (0, &[0x00]),
+        (1, &[0x01]),
+        (127, &[0x7f]),
+        (128, &[0x80, 0x01]),
+        (255, &[0xff, 0x01]),
+        (25565, &[0xdd, 0xc7, 0x01]),
+        (2097151, &[0xff, 0xff, 0x7f]),
+        (i32::MAX, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+        (-1, &[0xff, 0xff, 0xff, 0xff, 0x0f]),
+        (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+    ];
+
+    #[test]
+    fn varint_encode_decode() {
+        for &(value, bytes) in VARINTS {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value);
+            assert_eq!(buf, bytes, "{value}");
+            let mut cursor = bytes;
+            assert_eq!(read_varint(&mut cursor).unwrap(), value);
+            assert!(cursor.is_empty());
+        }
+    }
+
+    #[test]
+    fn varint_rejects_invalid() {
+        assert!(read_varint(&mut [].as_slice()).is_err());
+        assert!(read_varint(&mut [0x80, 0x80].as_slice()).is_err());
+        assert!(read_varint(&mut [0xff; 6].as_slice()).is_err());
+    }
+
+    #[test]
+    fn motd_to_plain_text() {
+        let description = serde_json::json!({
+            "text": "§aA ",
+            "extra": [{"text": "Minecraft"}, " §lServer"]
+        });
+        assert_eq!(
+            strip_formatting(&chat_text(&description)),
+            "A Minecraft Server"
+        );
+        assert_eq!(chat_text(&serde_json::json!("§6Legacy")), "§6Legacy");
+    }
+
+    #[tokio::test]
+    async fn ping_reads_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handshake = read_packet(&mut stream).await.unwrap();
+            let mut cursor = handshake.as_slice();
+            assert_eq!(read_varint(&mut cursor).unwrap(), 0x00);
+            assert_eq!(read_varint(&mut cursor).unwrap(), -1);
+            assert_eq!(read_packet(&mut stream).await.unwrap(), [0x00]);
+
+            let json = r#"{"version":{"name":"1.21.1","protocol":767},
+                "players":{"max":20,"online":3},"description":"§eHello"}"#;
+            let mut status = Vec::new();
+            write_varint(&mut status, 0x00);
+            write_string(&mut status, json);
+            send_packet(&mut stream, &status).await.unwrap();
+
+            let ping = read_packet(&mut stream).await.unwrap();
+            assert_eq!(ping[0], 0x01);
+            send_packet(&mut stream, &ping).await.unwrap();
+        });
+
+        let status = ping("127.0.0.1", port, Duration::from_secs(5))
+            .await
+            .unwrap();
+        server.await.unwrap();
+        assert_eq!(status.version, "1.21.1");
+        assert_eq!(status.protocol, 767);
+        assert_eq!((status.online, status.max), (3, 20));
+        assert_eq!(status.motd, "Hello");
+    }
+}
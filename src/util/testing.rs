@@ -0,0 +1,70 @@
+// 测试共用的工具
+
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use tokio::runtime::Runtime;
+
+/// 创建独立的临时目录
+pub fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("toymine-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 在共用的运行时中执行
+/// TASK_MANAGER 的管理任务运行在首次使用它的运行时中，用到它的测试必须共用同一个运行时
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build the test runtime")
+    });
+    RUNTIME.block_on(future)
+}
+
+/// 本地模拟 HTTP 服务端的处理函数，参数为请求头，返回 None 时保持连接但不响应
+pub type HttpHandler = Box<dyn Fn(&str) -> Option<Vec<u8>> + Send + Sync>;
+
+/// 启动本地模拟的 HTTP 服务端，返回形如 http://127.0.0.1:port 的地址
+pub async fn http_server(handler: HttpHandler) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let handler = std::sync::Arc::new(handler);
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let handler = std::sync::Arc::clone(&handler);
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                match handler(&String::from_utf8_lossy(&request)) {
+                    Some(response) => {
+                        let _ = socket.write_all(&response).await;
+                    }
+                    None => std::future::pending().await,
+                }
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+/// 构造 HTTP 响应，headers 的每行以 \r\n 结尾
+pub fn http_response(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
@@ -1,22 +1,47 @@
 use crate::core::mc_server::base::McServer;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::process::Command;
+use tracing::debug;
 
-pub struct BDS;
+pub struct BDS {
+    server_path: PathBuf,
+}
 
 impl McServer for BDS {
     fn new(path: &Path) -> Box<dyn McServer>
     where
         Self: Sized,
     {
-        todo!()
+        debug!("BDS");
+        Box::new(BDS {
+            server_path: path.to_path_buf(),
+        })
     }
 
     fn script(&self) -> anyhow::Result<String> {
-        todo!()
+        let mut s = String::new();
+        if std::env::consts::OS == "windows" {
+            s.push_str("@echo off\n");
+            s.push_str(format!("{}\n", self.server_path.to_string_lossy()).as_str());
+        } else {
+            // BDS 依赖同目录下的动态库
+            s.push_str("#!/bin/env bash\n");
+            s.push_str(
+                format!(
+                    "LD_LIBRARY_PATH=. {}\n",
+                    Path::new(".").join(&self.server_path).to_string_lossy()
+                )
+                .as_str(),
+            );
+        }
+        Ok(s)
     }
 
     fn start(&self) -> anyhow::Result<Command> {
-        todo!()
+        let mut command = Command::new(Path::new(".").join(&self.server_path));
+        if std::env::consts::OS != "windows" {
+            command.env("LD_LIBRARY_PATH", ".");
+        }
+        Ok(command)
     }
 }
@@ -1,13 +1,15 @@
 use crate::core::config::project::McServerConfig;
-use crate::core::mc_server::McType::{Bedrock, Java};
-use crate::core::mc_server::base::McServer;
+use crate::core::mc_server::McType::{self, Bedrock, Java};
+use crate::core::mc_server::base::{McServer, McVersion};
 use crate::versions::bds::BDS;
-use crate::versions::paper_like::{PAPER_MAP, PaperLike};
+use crate::versions::paper_like::PaperLike;
 use crate::versions::pumpkin::Pumpkin;
-use crate::versions::quick_analyze::analyze_je_game;
+pub use crate::versions::quick_analyze::world_version;
+use crate::versions::quick_analyze::{analyze_bds_game, analyze_je_game};
 use crate::versions::vanilla::Vanilla;
-use anyhow::{Result, anyhow};
-use std::path::Path;
+use anyhow::{Context, Result, anyhow};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
 
 mod bds;
 mod paper_like;
@@ -17,78 +19,287 @@ pub mod vanilla;
 
 pub struct VersionManager;
 
-impl VersionManager {
-    pub fn detect_server() -> Result<Option<Box<dyn McServer>>> {
-        let jar = Path::new("server.jar");
-        let bds = Path::new(match std::env::consts::OS {
-            "windows" => "bedrock_server.exe",
-            _ => "bedrock_server",
-        });
-        let pum = Path::new(match std::env::consts::OS {
-            "windows" => "pumpkin.exe",
-            _ => "pumpkin",
-        });
+/// 服务端类型的描述
+/// 安装和更新由 constructor 创建的实例通过 McServerUpdate 提供
+pub struct ServerKind {
+    /// McType 中的名称
+    pub name: &'static str,
+    /// 是否为基岩版
+    pub bedrock: bool,
+    /// 项目目录中的服务端文件，原生可执行文件在 Windows 下追加 .exe
+    file: &'static str,
+    /// Jar 的主类，用于识别打包了原始服务端的 Jar
+    pub main_classes: &'static [&'static str],
+    /// 版本和下载信息的 API 地址，不支持安装时为 None
+    pub api: Option<&'static str>,
+    /// 识别服务端文件的版本
+    detect: fn(&Path) -> Result<McVersion>,
+    constructor: fn(&Path, &'static ServerKind) -> Box<dyn McServer>,
+}
+
+/// 服务端注册表，新增服务端类型只需添加一项
+pub const SERVERS: &[ServerKind] = &[
+    ServerKind {
+        name: "vanilla",
+        bedrock: false,
+        file: "server.jar",
+        main_classes: &["net.minecraft.bundler.Main"],
+        api: Some(vanilla::MANIFEST_URL),
+        detect: analyze_je_game,
+        constructor: |path, _| Vanilla::new(path),
+    },
+    ServerKind {
+        name: "paper",
+        bedrock: false,
+        file: "server.jar",
+        main_classes: &["io.papermc.paperclip.Main"],
+        api: Some(paper_like::FILL_API),
+        detect: analyze_je_game,
+        constructor: PaperLike::from_project,
+    },
+    ServerKind {
+        name: "purpur",
+        bedrock: false,
+        file: "server.jar",
+        main_classes: &["io.papermc.paperclip.Main"],
+        api: Some(paper_like::FILL_API),
+        detect: analyze_je_game,
+        constructor: PaperLike::from_project,
+    },
+    ServerKind {
+        name: "folia",
+        bedrock: false,
+        file: "server.jar",
+        main_classes: &["io.papermc.paperclip.Main"],
+        api: Some(paper_like::FILL_API),
+        detect: analyze_je_game,
+        constructor: PaperLike::from_project,
+    },
+    ServerKind {
+        name: "leaves",
+        bedrock: false,
+        file: "server.jar",
+        main_classes: &["org.leavesmc.leavesclip.Main"],
+        api: Some(paper_like::FILL_API),
+        detect: analyze_je_game,
+        constructor: PaperLike::from_project,
+    },
+    ServerKind {
+        name: "pumpkin",
+        bedrock: false,
+        file: "pumpkin",
+        main_classes: &[],
+        api: None,
+        detect: |_| {
+            Err(anyhow!(
+                "Version detection for Pumpkin is not supported, please set the version manually"
+            ))
+        },
+        constructor: |path, _| Pumpkin::new(path),
+    },
+    ServerKind {
+        name: "bds",
+        bedrock: true,
+        file: "bedrock_server",
+        main_classes: &[],
+        api: None,
+        detect: analyze_bds_game,
+        constructor: |path, _| BDS::new(path),
+    },
+];
+
+impl ServerKind {
+    /// 服务端文件是否为 Jar，原生可执行文件为 false
+    pub fn is_jar(&self) -> bool {
+        self.file.ends_with(".jar")
+    }
+    /// 当前平台下的服务端文件
+    fn file(&self) -> PathBuf {
+        match (self.is_jar(), std::env::consts::OS) {
+            (false, "windows") => PathBuf::from(format!("{}.exe", self.file)),
+            _ => PathBuf::from(self.file),
+        }
+    }
+    /// 按服务端类型查找注册项
+    pub fn find(server_type: &McType) -> Option<&'static ServerKind> {
+        let (bedrock, name) = match server_type {
+            Java(name) => (false, name),
+            Bedrock(name) => (true, name),
+        };
+        SERVERS
+            .iter()
+            .find(|k| k.bedrock == bedrock && k.name == name)
+    }
+}
 
+impl VersionManager {
+    /// 在当前目录查找服务端文件，返回第一个使用该文件的注册项
+    fn locate_server() -> Result<Option<(&'static ServerKind, PathBuf)>> {
         let jar_mime = "application/zip";
         let bin_mime = match std::env::consts::OS {
             "windows" => "application/vnd.microsoft.portable-executable",
             _ => "application/x-executable",
         };
 
-        let is_jar =
-            jar.is_file() && infer::get_from_path(jar)?.is_some_and(|t| t.mime_type() == jar_mime);
-        let is_bds =
-            bds.is_file() && infer::get_from_path(bds)?.is_some_and(|t| t.mime_type() == bin_mime);
-        let is_pum =
-            pum.is_file() && infer::get_from_path(pum)?.is_some_and(|t| t.mime_type() == bin_mime);
-
-        let find_count = [is_jar, is_bds, is_pum].into_iter().filter(|&x| x).count();
-        if find_count == 0 {
-            return Ok(None);
-        } else if find_count > 1 {
+        let mut found: Vec<(&'static ServerKind, PathBuf)> = Vec::new();
+        for kind in SERVERS {
+            let file = kind.file();
+            // 多个类型共用同一个服务端文件，例如 server.jar
+            if found.iter().any(|(_, f)| *f == file) {
+                continue;
+            }
+            if Self::probe(&file, if kind.is_jar() { jar_mime } else { bin_mime })? {
+                found.push((kind, file));
+            }
+        }
+        if found.len() > 1 {
             return Err(anyhow!("Find multiple servers"));
         }
+        Ok(found.pop())
+    }
+    /// 判断文件是否存在且为预期的类型，读取失败时保留原因
+    fn probe(path: &Path, mime: &str) -> Result<bool> {
+        if !path.is_file() {
+            return Ok(false);
+        }
+        let kind = infer::get_from_path(path)
+            .with_context(|| format!("Failed to inspect {}", path.display()))?;
+        match kind {
+            Some(t) if t.mime_type() == mime => Ok(true),
+            Some(t) => {
+                warn!("{} is {}, expected {}", path.display(), t.mime_type(), mime);
+                Ok(false)
+            }
+            None => {
+                warn!("{} has an unrecognized file type", path.display());
+                Ok(false)
+            }
+        }
+    }
+    pub fn detect_server() -> Result<Option<Box<dyn McServer>>> {
+        Ok(match Self::locate_server()? {
+            None => None,
+            // Jar 由版本信息决定具体的类型
+            Some((kind, file)) => Self::construct(&(kind.detect)(&file)?.server_type, &file),
+        })
+    }
+    /// 从当前目录的服务端文件生成配置
+    pub fn detect_config() -> Result<McServerConfig> {
+        let Some((kind, server_file)) = Self::locate_server()? else {
+            return Err(anyhow!("MC Server Not Found"));
+        };
+        let version = (kind.detect)(&server_file)?;
 
-        if is_jar {
-            return Ok(match analyze_je_game(jar)?.server_type {
-                Java(s) => {
-                    if s.as_str() == "vanilla" {
-                        Some(Vanilla::new(jar))
-                    } else if PAPER_MAP.iter().filter(|&x| x.name == s.as_str()).count() != 0 {
-                        Some(PaperLike::new(jar))
-                    } else {
-                        None
-                    }
-                }
-                Bedrock(_) => unreachable!(),
-            });
+        let mut cfg = McServerConfig::new();
+        // 默认使用目录名作为项目名称
+        if let Some(name) = std::env::current_dir()?.file_name() {
+            cfg.project.name = name.to_string_lossy().to_string();
         }
-        if is_bds {
-            return Ok(Some(BDS::new(bds)));
+        cfg.project.version = version;
+        cfg.project.server_file = server_file;
+        Ok(cfg)
+    }
+    /// 服务端类型是否以 Jar 作为服务端文件，未注册的 Java 版类型视为 Jar
+    pub fn expects_jar(server_type: &McType) -> bool {
+        match ServerKind::find(server_type) {
+            Some(kind) => kind.is_jar(),
+            None => matches!(server_type, Java(_)),
         }
-        if is_pum {
-            return Ok(Some(Pumpkin::new(pum)));
+    }
+    /// 按服务端类型创建实例，未注册的类型为 None
+    fn construct(server_type: &McType, path: &Path) -> Option<Box<dyn McServer>> {
+        ServerKind::find(server_type).map(|k| (k.constructor)(path, k))
+    }
+    pub fn from_cfg(cfg: &McServerConfig) -> Option<Box<dyn McServer>> {
+        let mut server =
+            Self::construct(&cfg.project.version.server_type, &cfg.project.server_file)?;
+        // 加载版本内部配置
+        if let Err(e) = server.load_config(&mut <dyn erased_serde::Deserializer>::erase(
+            cfg.inner.clone(),
+        )) {
+            warn!("Failed to load the server configuration: {e}");
         }
+        Some(server)
+    }
+}
 
-        unreachable!()
+/// 备份旧的服务端文件，返回备份路径
+/// 形如 `server.jar.bak`，已存在时依次尝试 `server.jar.1.bak` `server.jar.2.bak`
+/// 符号链接本身被移动，不影响链接指向的文件
+pub async fn backup_server_file(path: &Path) -> Result<Option<PathBuf>> {
+    // 使用 symlink_metadata 以便处理失效的符号链接
+    if tokio::fs::symlink_metadata(path).await.is_err() {
+        return Ok(None);
     }
-    pub fn detect_config() -> Result<McServerConfig> {
-        todo!()
+    let name = path.to_string_lossy();
+    let mut backup = PathBuf::from(format!("{name}.bak"));
+    let mut count = 1;
+    while tokio::fs::symlink_metadata(&backup).await.is_ok() {
+        backup = PathBuf::from(format!("{name}.{count}.bak"));
+        count += 1;
     }
-    pub fn from_cfg(cfg: &McServerConfig) -> Option<Box<dyn McServer>> {
-        match &cfg.project.version.server_type {
-            Java(s) => {
-                if s == "vanilla" {
-                    Some(Vanilla::new(&cfg.project.server_file))
-                } else if PAPER_MAP.iter().filter(|&x| x.name == s.as_str()).count() != 0 {
-                    Some(PaperLike::new(&cfg.project.server_file))
-                } else if s == "pumpkin" {
-                    Some(Pumpkin::new(&cfg.project.server_file))
-                } else {
-                    None
-                }
-            }
-            Bedrock(_) => Some(BDS::new(&cfg.project.server_file)),
+    tokio::fs::rename(path, &backup).await?;
+    info!("The old server file has been moved to {}", backup.display());
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::temp_dir;
+
+    #[test]
+    fn registry_lookup() {
+        let bds = ServerKind::find(&Bedrock("bds".to_string())).unwrap();
+        assert!(bds.bedrock && !bds.is_jar());
+        assert!(ServerKind::find(&Java("bds".to_string())).is_none());
+        assert!(ServerKind::find(&Java("purpur".to_string())).is_some_and(|k| k.is_jar()));
+        // 未注册的 Java 版类型视为 Jar
+        assert!(VersionManager::expects_jar(&Java("spigot".to_string())));
+        assert!(!VersionManager::expects_jar(&Java("pumpkin".to_string())));
+        assert!(!VersionManager::expects_jar(&Bedrock("bds".to_string())));
+        // 同一类型的服务端不会重复注册
+        for (i, kind) in SERVERS.iter().enumerate() {
+            assert!(
+                SERVERS[..i]
+                    .iter()
+                    .all(|k| (k.bedrock, k.name) != (kind.bedrock, kind.name))
+            );
         }
     }
+
+    #[tokio::test]
+    async fn backups_are_numbered() {
+        let dir = temp_dir();
+        let jar = dir.join("server.jar");
+        assert_eq!(backup_server_file(&jar).await.unwrap(), None);
+
+        for (i, expected) in ["server.jar.bak", "server.jar.1.bak", "server.jar.2.bak"]
+            .into_iter()
+            .enumerate()
+        {
+            std::fs::write(&jar, i.to_string()).unwrap();
+            let backup = backup_server_file(&jar).await.unwrap().unwrap();
+            assert_eq!(backup, dir.join(expected));
+            assert_eq!(std::fs::read_to_string(&backup).unwrap(), i.to_string());
+            assert!(!jar.exists());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn dangling_symlink_is_moved() {
+        let dir = temp_dir();
+        let jar = dir.join("server.jar");
+        let target = dir.join("missing.jar");
+        std::os::unix::fs::symlink(&target, &jar).unwrap();
+
+        let backup = backup_server_file(&jar).await.unwrap().unwrap();
+        assert_eq!(backup, dir.join("server.jar.bak"));
+        assert_eq!(std::fs::read_link(&backup).unwrap(), target);
+        assert!(std::fs::symlink_metadata(&jar).is_err());
+        assert!(!target.exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -1,38 +1,180 @@
-use crate::core::mc_server::McVersion;
 use crate::core::mc_server::base::McServer;
 use crate::core::mc_server::runtime::McServerRuntime;
 use crate::core::mc_server::update::McServerUpdate;
-use crate::versions::quick_analyze::analyze_jar;
+use crate::core::mc_server::{McChannel, McType, McVersion};
+use crate::runtime::java::GLOBAL_JAVA;
+use crate::util::downloader::{Downloader, move_file};
+use crate::versions::quick_analyze::{analyze_jar, analyze_je_game};
+use crate::versions::{ServerKind, backup_server_file};
+use anyhow::{Context, anyhow};
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 
-pub struct PaperConst {
-    pub name: &'static str,
-    pub main_class: &'static str,
+/// PaperMC Fill API
+pub(super) const FILL_API: &str = "https://fill.papermc.io/v3/projects";
+
+/// Fill API 上的项目
+pub struct PaperProject {
+    name: String,
+    /// API 地址，测试时指向本地服务端
+    api: String,
+}
+
+#[derive(Deserialize)]
+struct ProjectInfo {
+    /// 版本组 -> 版本列表
+    versions: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct BuildInfo {
+    pub id: u32,
+    /// ALPHA / BETA / STABLE / RECOMMENDED
+    pub channel: String,
+    downloads: HashMap<String, DownloadInfo>,
+}
+
+#[derive(Deserialize)]
+struct DownloadInfo {
+    url: String,
+    checksums: Checksums,
+}
+
+#[derive(Deserialize)]
+struct Checksums {
+    sha256: String,
+}
+
+impl BuildInfo {
+    /// 是否为稳定构建
+    pub fn is_stable(&self) -> bool {
+        matches!(self.channel.as_str(), "STABLE" | "RECOMMENDED")
+    }
+}
+
+impl PaperProject {
+    pub fn new(name: &str, api: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            api: api.to_string(),
+        }
+    }
+    /// 获取项目支持的全部版本
+    pub async fn versions(&self) -> anyhow::Result<Vec<String>> {
+        let text = Downloader::new()
+            .await
+            .get(format!("{}/{}", self.api, self.name))
+            .await?
+            .with_successful_status()?
+            .text()
+            .await?;
+        let info: ProjectInfo = serde_json::from_str(&text)?;
+        Ok(info.versions.into_values().flatten().collect())
+    }
+    /// 获取指定版本的全部构建
+    pub async fn builds(&self, version: &str) -> anyhow::Result<Vec<BuildInfo>> {
+        let text = Downloader::new()
+            .await
+            .get(format!(
+                "{}/{}/versions/{version}/builds",
+                self.api, self.name
+            ))
+            .await?
+            .with_successful_status()?
+            .text()
+            .await?;
+        Ok(serde_json::from_str(&text)?)
+    }
+    /// 获取指定版本最新的稳定构建
+    pub async fn latest_build(&self, version: &str) -> anyhow::Result<BuildInfo> {
+        let builds = self.builds(version).await?;
+        latest_stable(builds).ok_or(anyhow!("No stable build for {} {}", self.name, version))
+    }
+    /// 获取有稳定构建的最高正式版本
+    /// 只有实验性构建的新版本会被跳过，避免升级到不稳定的构建
+    pub async fn latest_version(&self) -> anyhow::Result<McChannel> {
+        let mut versions = self
+            .versions()
+            .await?
+            .into_iter()
+            .filter_map(|v| {
+                let parts = v
+                    .split('.')
+                    .map(|x| x.parse::<u8>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .ok()?;
+                match parts[..] {
+                    [major, minor] => Some((v, (major, minor, 0))),
+                    [major, minor, patch] => Some((v, (major, minor, patch))),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        versions.sort_by_key(|(_, v)| std::cmp::Reverse(*v));
+        for (version, (major, minor, patch)) in versions {
+            if latest_stable(self.builds(&version).await?).is_some() {
+                return Ok(McChannel::Release(major, minor, patch));
+            }
+            debug!("{} {} has no stable build yet", self.name, version);
+        }
+        Err(anyhow!("Failed to get the latest version of {}", self.name))
+    }
+    /// 获取指定的构建
+    pub async fn build(&self, version: &str, id: u32) -> anyhow::Result<BuildInfo> {
+        self.builds(version)
+            .await?
+            .into_iter()
+            .find(|b| b.id == id)
+            .ok_or(anyhow!("No such build: {} {} #{}", self.name, version, id))
+    }
+}
+
+/// 最新的稳定构建
+fn latest_stable(builds: Vec<BuildInfo>) -> Option<BuildInfo> {
+    builds
+        .into_iter()
+        .filter(BuildInfo::is_stable)
+        .max_by_key(|b| b.id)
 }
-pub const PAPER_MAP: &[PaperConst] = &[
-    PaperConst {
-        name: "paper",
-        main_class: "io.papermc.paperclip.Main",
-    },
-    PaperConst {
-        name: "purpur",
-        main_class: "io.papermc.paperclip.Main",
-    },
-    PaperConst {
-        name: "folia",
-        main_class: "io.papermc.paperclip.Main",
-    },
-    PaperConst {
-        name: "leaves",
-        main_class: "org.leavesmc.leavesclip.Main",
-    },
-];
 
 pub struct PaperLike {
-    runtime_path: PathBuf,
+    /// java 可执行文件，准备运行时后指向找到或安装的运行时
+    runtime_path: ArcSwap<PathBuf>,
     server_path: PathBuf,
+    name: String,
+    /// 注册表中的 API 地址
+    api: &'static str,
+    cfg: PaperCfg,
+}
+
+/// 持久化的内部配置
+#[derive(Serialize, Deserialize, Default)]
+struct PaperCfg {
+    /// 固定的构建号，未设置时使用最新的稳定构建
+    /// 只影响 install_version，latest_version 不考虑固定的构建
+    build: Option<u32>,
+}
+
+impl PaperLike {
+    /// 按注册项创建实例
+    pub fn from_project(path: &Path, kind: &'static ServerKind) -> Box<dyn McServer> {
+        debug!("PaperLike");
+        Box::new(PaperLike {
+            runtime_path: ArcSwap::from_pointee(PathBuf::from("java")),
+            server_path: path.to_path_buf(),
+            name: kind.name.to_string(),
+            api: kind.api.unwrap_or(FILL_API),
+            cfg: Default::default(),
+        })
+    }
+    fn project(&self) -> PaperProject {
+        PaperProject::new(&self.name, self.api)
+    }
 }
 
 impl McServer for PaperLike {
@@ -40,11 +182,18 @@ impl McServer for PaperLike {
     where
         Self: Sized,
     {
-        debug!("PaperLike");
-        Box::new(PaperLike {
-            runtime_path: "java".parse().unwrap(),
-            server_path: path.to_path_buf(),
-        })
+        let server_type = analyze_je_game(path).map(|v| v.server_type);
+        let kind = match server_type.as_ref().ok().and_then(ServerKind::find) {
+            Some(kind) if kind.api == Some(FILL_API) => kind,
+            _ => {
+                warn!(
+                    "{} is not a known PaperMC project, treated as paper",
+                    path.display()
+                );
+                ServerKind::find(&McType::Java("paper".to_string())).unwrap()
+            }
+        };
+        Self::from_project(path, kind)
     }
 
     fn script(&self) -> anyhow::Result<String> {
@@ -52,7 +201,7 @@ impl McServer for PaperLike {
     }
 
     fn start(&self) -> anyhow::Result<tokio::process::Command> {
-        let mut command = tokio::process::Command::new("java");
+        let mut command = tokio::process::Command::new(self.runtime_path.load().as_path());
         command.arg("-jar").arg("server.jar").arg("-nogui");
         Ok(command)
     }
@@ -62,16 +211,49 @@ impl McServer for PaperLike {
     fn impl_runtime<'a>(&'a self) -> Option<&'a dyn McServerRuntime> {
         Some(self)
     }
+    fn to_config(&self) -> anyhow::Result<Box<dyn erased_serde::Serialize + '_>> {
+        Ok(Box::new(&self.cfg))
+    }
+    fn load_config(&mut self, de: &mut dyn erased_serde::Deserializer) -> anyhow::Result<()> {
+        self.cfg = erased_serde::deserialize(de)?;
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl McServerUpdate for PaperLike {
     async fn latest_version(&self) -> anyhow::Result<McVersion> {
-        todo!()
+        Ok(McVersion {
+            server_type: McType::Java(self.name.clone()),
+            channel: self.project().latest_version().await?,
+        })
     }
 
     async fn install_version(&self, target: McVersion) -> anyhow::Result<()> {
-        todo!()
+        let version = match target.channel {
+            McChannel::Release(major, minor, 0) => format!("{major}.{minor}"),
+            McChannel::Release(major, minor, patch) => format!("{major}.{minor}.{patch}"),
+            McChannel::Snapshot(v) => v,
+            McChannel::Unknown => return Err(anyhow!("Unknown target version")),
+        };
+        let project = self.project();
+        let build = match self.cfg.build {
+            Some(id) => project.build(&version, id).await?,
+            None => project.latest_build(&version).await?,
+        };
+        let download = build
+            .downloads
+            .get("server:default")
+            .context("No server download in this build")?;
+        info!("Installing {} {} build #{}", self.name, version, build.id);
+        let file = Downloader::new()
+            .await
+            .download_with_sha256(download.url.clone(), download.checksums.sha256.as_str())
+            .await?;
+        // 保留旧的服务端
+        backup_server_file(&self.server_path).await?;
+        move_file(&file, &self.server_path).await?;
+        Ok(())
     }
 }
 
@@ -79,17 +261,21 @@ impl McServerUpdate for PaperLike {
 impl McServerRuntime for PaperLike {
     async fn ready_runtime(&self) -> anyhow::Result<bool> {
         debug!("Check runtime");
-        Ok(!crate::runtime::java::GLOBAL_JAVA
-            .check(analyze_jar(&self.server_path)?.java_version as usize)
-            .await
-            .is_empty())
+        let version = analyze_jar(&self.server_path)?.java_version as usize;
+        match GLOBAL_JAVA.check(version).await.into_iter().next() {
+            Some((java, _)) => {
+                self.runtime_path.store(Arc::new(java));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
     async fn setup_runtime(&self) -> anyhow::Result<()> {
         debug!("Install runtime");
-        crate::runtime::java::GLOBAL_JAVA
-            .install(analyze_jar(&self.server_path)?.java_version as usize)
-            .await?;
+        let version = analyze_jar(&self.server_path)?.java_version as usize;
+        let java = GLOBAL_JAVA.install(version).await?;
+        self.runtime_path.store(Arc::new(java));
         Ok(())
     }
 
@@ -103,7 +289,7 @@ impl McServerRuntime for PaperLike {
         s.push_str(
             format!(
                 "{} -jar {} -nogui",
-                self.runtime_path.to_string_lossy(),
+                self.runtime_path.load().to_string_lossy(),
                 self.server_path.to_string_lossy()
             )
             .as_str(),
@@ -111,3 +297,75 @@ impl McServerRuntime for PaperLike {
         Ok(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::{http_response, http_server};
+
+    #[test]
+    fn latest_stable_skips_experimental_builds() {
+        let builds: Vec<BuildInfo> = serde_json::from_str(
+            r#"[
+                {"id": 1, "channel": "STABLE", "downloads": {}},
+                {"id": 3, "channel": "RECOMMENDED", "downloads": {}},
+                {"id": 4, "channel": "BETA", "downloads": {}},
+                {"id": 2, "channel": "STABLE", "downloads": {}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(latest_stable(builds).map(|b| b.id), Some(3));
+
+        let builds: Vec<BuildInfo> =
+            serde_json::from_str(r#"[{"id": 7, "channel": "ALPHA", "downloads": {}}]"#).unwrap();
+        assert!(latest_stable(builds).is_none());
+        assert!(latest_stable(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn launch_uses_the_resolved_java() {
+        let server = PaperLike {
+            runtime_path: ArcSwap::from_pointee(PathBuf::from("java")),
+            server_path: PathBuf::from("server.jar"),
+            name: "paper".to_string(),
+            api: FILL_API,
+            cfg: Default::default(),
+        };
+        let java = PathBuf::from("/opt/jdk/Contents/Home/bin/java");
+        server.runtime_path.store(Arc::new(java.clone()));
+        assert_eq!(server.start().unwrap().as_std().get_program(), java);
+        let script = server.ext_script("x86_64", "linux").unwrap();
+        assert!(script.contains(&format!("{} -jar", java.display())));
+    }
+
+    /// 1.21.10 只有实验性构建，1.21.9 没有构建，1.21.8 有稳定构建
+    fn fill_api(request: &str) -> Option<Vec<u8>> {
+        let path = request.split(' ').nth(1)?;
+        let body = match path {
+            "/paper" => {
+                r#"{"versions": {"1.21": ["1.21.10", "1.21.9", "1.21.8", "1.21.7"], "1.20": ["1.20.6", "1.20.5-rc1"]}}"#
+            }
+            "/paper/versions/1.21.10/builds" => {
+                r#"[{"id": 2, "channel": "BETA", "downloads": {}}, {"id": 1, "channel": "ALPHA", "downloads": {}}]"#
+            }
+            "/paper/versions/1.21.9/builds" => "[]",
+            "/paper/versions/1.21.8/builds" => {
+                r#"[{"id": 60, "channel": "STABLE", "downloads": {}}]"#
+            }
+            _ => return Some(http_response("404 Not Found", "", b"")),
+        };
+        Some(http_response("200 OK", "", body.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn latest_version_skips_experimental_versions() {
+        let project = PaperProject {
+            name: "paper".to_string(),
+            api: http_server(Box::new(fill_api)).await,
+        };
+        assert!(matches!(
+            project.latest_version().await.unwrap(),
+            McChannel::Release(1, 21, 8)
+        ));
+    }
+}
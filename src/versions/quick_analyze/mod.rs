@@ -3,9 +3,10 @@
 use crate::core::mc_server::base::McVersion;
 
 use crate::core::mc_server::{McChannel, McType};
-use crate::versions::paper_like::PAPER_MAP;
+use crate::versions::SERVERS;
 use anyhow::{Error, Result, anyhow};
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Cursor, Read};
 use std::path::Path;
@@ -91,26 +92,26 @@ pub fn analyze_je_game(jar_path: &Path) -> Result<McVersion> {
     let mut archive = ZipArchive::new(&file)?;
 
     // 判断主类格式
-    if info.main_class == "net.minecraft.bundler.Main"
-        || PAPER_MAP
-            .iter()
-            .filter(|&x| x.main_class == info.main_class)
-            .count()
-            != 0
+    if SERVERS
+        .iter()
+        .any(|k| k.main_classes.contains(&info.main_class.as_str()))
     {
-        // 读取 `META-INF/versions.list`
-        let mut version_file = archive.by_name("META-INF/versions.list")?;
-        let mut version_list = String::new();
-        version_file.read_to_string(&mut version_list)?;
-
-        // 解析 `META-INF/versions.list`
-        // 形如 "2e2867d1c6559bdb660808deaeccb12c9ca41eb04e7b4e2adae87546e1878184	1.21.10	1.21.10/server-1.21.10.jar"
-        let info_list = version_list.split("/").collect::<Vec<_>>()[1].replace(".jar", "");
-        let info_list: Vec<&str> = info_list.split("-").collect();
-
-        // 解析版本号
-        if info_list.len() == 2 {
-            return Ok(parse_version(info_list[1].trim(), info_list[0].trim()));
+        // 读取 `META-INF/versions.list`，不存在时继续尝试其他方式
+        if let Ok(mut version_file) = archive.by_name("META-INF/versions.list") {
+            let mut version_list = String::new();
+            version_file.read_to_string(&mut version_list)?;
+
+            // 解析 `META-INF/versions.list`
+            // 形如 "2e2867d1c6559bdb660808deaeccb12c9ca41eb04e7b4e2adae87546e1878184	1.21.10	1.21.10/server-1.21.10.jar"
+            if let Some(file) = version_list.split("/").nth(1) {
+                let info_list = file.replace(".jar", "");
+                let info_list: Vec<&str> = info_list.split("-").collect();
+
+                // 解析版本号
+                if info_list.len() == 2 {
+                    return Ok(parse_version(info_list[1].trim(), info_list[0].trim()));
+                }
+            }
         }
     }
 
@@ -126,23 +127,14 @@ pub fn analyze_je_game(jar_path: &Path) -> Result<McVersion> {
         // 转换 version.json 为字符串
         let mut version_json_string = String::new();
         file.read_to_string(&mut version_json_string)?;
-        // 从 json 获得 name 键的值
-        // 找到 "name"
-        let key = "\"name\"";
-        let start = version_json_string.find(key).expect("Problematic JSON.") + key.len();
-        // 找到冒号
-        let after_colon = version_json_string[start..]
-            .find(':')
-            .expect("Problematic JSON.");
-        let rest = &version_json_string[start + after_colon + 1..];
-        // 去掉前面的空白和引号
-        let rest = rest.trim_start();
-        if rest.starts_with('"') {
-            // 普通字符串值
-            let end_quote = rest[1..].find('"').expect("Problematic JSON.");
-            let version = &rest[1..1 + end_quote];
-            // 解析版本号，默认当成 Vanilla
-            return Ok(parse_version(version, "vanilla"));
+        // 从 json 获得 name 键的值，格式有误时继续尝试其他方式
+        match serde_json::from_str::<serde_json::Value>(&version_json_string) {
+            Ok(json) => match json.get("name").and_then(|v| v.as_str()) {
+                // 解析版本号，默认当成 Vanilla
+                Some(version) => return Ok(parse_version(version, "vanilla")),
+                None => debug!("analyze_je_game:  \"version.json\" has no name"),
+            },
+            Err(e) => debug!("analyze_je_game:  Problematic \"version.json\": {e}"),
         }
     };
 
@@ -203,10 +195,80 @@ pub fn analyze_je_game(jar_path: &Path) -> Result<McVersion> {
     }
 
     Err(Error::msg(
-        "Version parsing failed: Version information cannot be found.",
+        "Version parsing failed: Unsupported jar format, please set the version manually.",
     ))
 }
 
+/// 分析 bedrock_server 可执行文件，尝试获得游戏版本
+pub fn analyze_bds_game(bin_path: &Path) -> Result<McVersion> {
+    // 版本号以可读字符串形式嵌入在二进制文件中，形如 "1.21.50.07"
+    debug!("analyze_bds_game:  Scan strings of bedrock_server");
+    let counter = count_bds_versions(File::open(bin_path)?)?;
+    match most_common_version(counter) {
+        Some(version) => {
+            let mut version = parse_version(&version, "bds");
+            version.server_type = McType::Bedrock("bds".to_string());
+            Ok(version)
+        }
+        None => Err(Error::msg(
+            "Version parsing failed: Version information cannot be found.",
+        )),
+    }
+}
+
+/// 版本号扫描的块大小，避免一次性读入上百 MB 的二进制文件
+const BDS_CHUNK: usize = 4 * 1024 * 1024;
+
+/// 分块扫描二进制数据中的版本号，返回每个版本号出现的次数
+fn count_bds_versions(mut reader: impl Read) -> Result<HashMap<String, usize>> {
+    let re = regex::bytes::Regex::new(r"(?-u)\b1\.[0-9]{1,2}\.[0-9]{1,3}\.[0-9]{1,3}\b")?;
+    const OVERLAP: usize = 32;
+    let mut buffer = vec![0u8; BDS_CHUNK + OVERLAP];
+    let mut carry = 0;
+    let mut counter: HashMap<String, usize> = HashMap::new();
+    loop {
+        let n = reader.read(&mut buffer[carry..carry + BDS_CHUNK])?;
+        let eof = n == 0;
+        let len = carry + n;
+        // 末尾部分保留到下一块重新扫描，避免版本号被块边界截断
+        let keep = if eof { 0 } else { len.min(OVERLAP) };
+        let limit = len - keep;
+        for m in re.find_iter(&buffer[..len]) {
+            if m.start() >= limit {
+                break;
+            }
+            *counter
+                .entry(String::from_utf8_lossy(m.as_bytes()).into_owned())
+                .or_default() += 1;
+        }
+        if eof {
+            break;
+        }
+        buffer.copy_within(limit..len, 0);
+        carry = keep;
+    }
+    Ok(counter)
+}
+
+/// 取出现次数最多的版本号，次数相同时取较高的版本，保证结果稳定
+fn most_common_version(counter: HashMap<String, usize>) -> Option<String> {
+    let key = |version: &str| -> Vec<u32> {
+        version
+            .split('.')
+            .map(|n| n.parse().unwrap_or_default())
+            .collect()
+    };
+    counter
+        .into_iter()
+        .max_by(|(a, a_count), (b, b_count)| {
+            a_count
+                .cmp(b_count)
+                .then_with(|| key(a).cmp(&key(b)))
+                .then_with(|| a.cmp(b))
+        })
+        .map(|(version, _)| version)
+}
+
 /// 从 ZipFile 读取 .class 文件并解析字符串常量池
 fn parse_class_strings_from_zip(file: &mut ZipFile<&File>) -> Vec<String> {
     fn read_u1(c: &mut Cursor<Vec<u8>>) -> Option<u8> {
@@ -272,7 +334,7 @@ fn parse_class_strings_from_zip(file: &mut ZipFile<&File>) -> Vec<String> {
     strings
 }
 
-fn parse_version(version_str: &str, version_type: &str) -> McVersion {
+pub fn parse_version(version_str: &str, version_type: &str) -> McVersion {
     let chanel = match version_str
         .split('.')
         .map(|x| x.parse::<u8>())
@@ -299,3 +361,101 @@ fn parse_version(version_str: &str, version_type: &str) -> McVersion {
         channel: chanel,
     }
 }
+
+/// 读取服务端的世界数据版本（version.json 的 world_version），1.14 之前的服务端没有
+/// Paperclip 等打包的服务端从 META-INF/versions 中的原始 jar 读取
+pub fn world_version(jar_path: &Path) -> Result<Option<i32>> {
+    fn read_version_json<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>) -> Option<i32> {
+        let mut s = String::new();
+        archive
+            .by_name("version.json")
+            .ok()?
+            .read_to_string(&mut s)
+            .ok()?;
+        let json: serde_json::Value = serde_json::from_str(&s).ok()?;
+        i32::try_from(json.get("world_version")?.as_i64()?).ok()
+    }
+
+    let mut archive = ZipArchive::new(File::open(jar_path)?)?;
+    if let Some(v) = read_version_json(&mut archive) {
+        return Ok(Some(v));
+    }
+    // 形如 "<sha256>\t1.21.1\t1.21.1/paper-1.21.1.jar"
+    let mut list = String::new();
+    match archive.by_name("META-INF/versions.list") {
+        Ok(mut f) => f.read_to_string(&mut list)?,
+        Err(_) => return Ok(None),
+    };
+    for path in list.lines().filter_map(|l| l.split('\t').nth(2)) {
+        let mut data = Vec::new();
+        archive
+            .by_name(&format!("META-INF/versions/{path}"))?
+            .read_to_end(&mut data)?;
+        if let Some(v) = read_version_json(&mut ZipArchive::new(Cursor::new(data))?) {
+            return Ok(Some(v));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bds_version_across_chunk_boundary() {
+        let version = b"1.21.50.07";
+        let mut data = vec![0u8; BDS_CHUNK * 2];
+        // 版本号跨越、结束于和开始于 4 MiB 的块边界
+        for start in [
+            BDS_CHUNK - 4,
+            BDS_CHUNK - version.len(),
+            BDS_CHUNK,
+            BDS_CHUNK * 2 - 4 - version.len(),
+        ] {
+            data[start..start + version.len()].copy_from_slice(version);
+            let counter = count_bds_versions(data.as_slice()).unwrap();
+            assert_eq!(
+                counter,
+                HashMap::from([("1.21.50.07".to_string(), 1)]),
+                "{start}"
+            );
+            data[start..start + version.len()].fill(0);
+        }
+    }
+
+    #[test]
+    fn bds_version_counts_every_occurrence() {
+        let data = b"x 1.21.50.07\0 1.21.50.07 1.20.1.2\0v1.2.3.4 11.2.3.4 1.2.3";
+        let counter = count_bds_versions(data.as_slice()).unwrap();
+        assert_eq!(
+            counter,
+            HashMap::from([("1.21.50.07".to_string(), 2), ("1.20.1.2".to_string(), 1),])
+        );
+    }
+
+    #[test]
+    fn most_common_version_prefers_higher_on_tie() {
+        let counter = |versions: &[(&str, usize)]| {
+            versions
+                .iter()
+                .map(|&(v, n)| (v.to_string(), n))
+                .collect::<HashMap<_, _>>()
+        };
+        assert_eq!(
+            most_common_version(counter(&[("1.21.2.1", 1), ("1.21.10.1", 3)])).as_deref(),
+            Some("1.21.10.1")
+        );
+        // 按数值而不是字符串比较
+        assert_eq!(
+            most_common_version(counter(&[
+                ("1.21.2.1", 2),
+                ("1.21.10.1", 2),
+                ("1.9.0.1", 2)
+            ]))
+            .as_deref(),
+            Some("1.21.10.1")
+        );
+        assert_eq!(most_common_version(HashMap::new()), None);
+    }
+}
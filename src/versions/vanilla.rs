@@ -1,16 +1,172 @@
+use crate::core::mc_server::McChannel;
 use crate::core::mc_server::base::{McServer, McVersion};
 use crate::core::mc_server::runtime::McServerRuntime;
 use crate::core::mc_server::update::McServerUpdate;
-use anyhow::Result;
+use crate::runtime::java::GLOBAL_JAVA;
+use crate::util::downloader::{Downloader, is_offline, move_file, with_host_headers};
+use crate::util::hash::Checksum;
+use crate::versions::backup_server_file;
+use crate::versions::quick_analyze::{analyze_jar, parse_version};
+use crate::{GLOBAL_CACHE, GLOBAL_CONFIG};
+use anyhow::{Context, Result, anyhow};
+use arc_swap::ArcSwap;
 use async_trait::async_trait;
-use serde::{Deserialize, Serialize};
+use nyquest::r#async::Request;
+use serde::Deserialize;
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Mojang 版本清单
+pub(super) const MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+#[derive(Deserialize)]
+pub struct VersionManifest {
+    pub latest: LatestVersion,
+    pub versions: Vec<ManifestVersion>,
+}
+
+#[derive(Deserialize)]
+pub struct LatestVersion {
+    pub release: String,
+}
+
+#[derive(Deserialize)]
+pub struct ManifestVersion {
+    pub id: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct VersionDetail {
+    downloads: VersionDownloads,
+}
+
+#[derive(Deserialize)]
+struct VersionDownloads {
+    server: Option<VersionDownload>,
+}
+
+#[derive(Deserialize)]
+struct VersionDownload {
+    url: String,
+    sha1: Option<String>,
+}
+
+impl VersionManifest {
+    /// 获取版本清单，使用全局配置中的缓存有效期
+    pub async fn fetch() -> Result<Self> {
+        Self::fetch_from(
+            Downloader::new().await,
+            MANIFEST_URL,
+            &GLOBAL_CACHE,
+            GLOBAL_CONFIG.download.manifest_ttl(),
+            is_offline(),
+        )
+        .await
+    }
+    /// 从 url 获取版本清单，缓存保存在 cache_dir
+    /// 缓存未过期时直接使用缓存，过期后通过 ETag 重新验证，网络不可用时回退到缓存
+    async fn fetch_from(
+        downloader: &Downloader,
+        url: &str,
+        cache_dir: &Path,
+        ttl: Duration,
+        offline: bool,
+    ) -> Result<Self> {
+        let cache = cache_dir.join("version_manifest_v2.json");
+        let etag_file = cache_dir.join("version_manifest_v2.etag");
+
+        let age = tokio::fs::metadata(&cache)
+            .await
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| SystemTime::now().duration_since(t).ok());
+        match CachePlan::new(age, ttl, offline) {
+            CachePlan::Unavailable => return Err(anyhow!("The version manifest is not cached")),
+            CachePlan::UseCache => {
+                debug!("Using cached version manifest");
+                return Self::from_cache(&cache).await;
+            }
+            CachePlan::Fetch => {}
+        }
+
+        let etag = match age {
+            Some(_) => tokio::fs::read_to_string(&etag_file).await.ok(),
+            None => None,
+        };
+        let request = || match &etag {
+            Some(etag) => with_host_headers(
+                Request::get(url.to_string()).with_header("If-None-Match", etag.clone()),
+                url,
+            ),
+            None => with_host_headers(Request::get(url.to_string()), url),
+        };
+        let response = match downloader.request(request).await {
+            Ok(v) => v,
+            Err(e) if age.is_some() => {
+                warn!("Failed to fetch the version manifest, using cache: {e}");
+                return Self::from_cache(&cache).await;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // 未修改，刷新缓存时间
+        if response.status().code() == 304 {
+            debug!("Version manifest not modified");
+            let text = tokio::fs::read_to_string(&cache).await?;
+            tokio::fs::write(&cache, &text).await?;
+            return Ok(serde_json::from_str(&text)?);
+        }
+
+        let etag = response.get_header("etag")?.into_iter().next();
+        let text = response.with_successful_status()?.text().await?;
+        let manifest = serde_json::from_str(&text)?;
+        tokio::fs::write(&cache, &text).await?;
+        match etag {
+            Some(etag) => tokio::fs::write(&etag_file, etag).await?,
+            None => {
+                let _ = tokio::fs::remove_file(&etag_file).await;
+            }
+        }
+        Ok(manifest)
+    }
+    async fn from_cache(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(
+            &tokio::fs::read_to_string(path).await?,
+        )?)
+    }
+}
+
+/// 根据缓存状态决定如何获取版本清单
+#[derive(Debug, PartialEq)]
+enum CachePlan {
+    /// 直接使用缓存，不访问网络
+    UseCache,
+    /// 请求网络，有缓存时通过 ETag 重新验证
+    Fetch,
+    /// 离线且没有缓存
+    Unavailable,
+}
+
+impl CachePlan {
+    /// age 为缓存的存在时间，没有缓存时为 None
+    fn new(age: Option<Duration>, ttl: Duration, offline: bool) -> Self {
+        match age {
+            None if offline => Self::Unavailable,
+            Some(_) if offline => Self::UseCache,
+            Some(age) if age < ttl => Self::UseCache,
+            _ => Self::Fetch,
+        }
+    }
+}
 
-#[derive(Serialize, Deserialize)]
 pub struct Vanilla {
     server_path: PathBuf,
-    runtime_path: PathBuf,
+    /// java 可执行文件，准备运行时后指向找到或安装的运行时
+    runtime_path: ArcSwap<PathBuf>,
 }
 
 #[async_trait]
@@ -22,7 +178,7 @@ impl McServer for Vanilla {
         debug!("Vanilla");
         Box::new(Vanilla {
             server_path: path.to_path_buf(),
-            runtime_path: "java".parse().unwrap(),
+            runtime_path: ArcSwap::from_pointee(PathBuf::from("java")),
         })
     }
 
@@ -31,7 +187,7 @@ impl McServer for Vanilla {
     }
 
     fn start(&self) -> Result<tokio::process::Command> {
-        let mut command = tokio::process::Command::new(&self.runtime_path);
+        let mut command = tokio::process::Command::new(self.runtime_path.load().as_path());
         command.arg("-jar").arg(&self.server_path).arg("-nogui");
         Ok(command)
     }
@@ -39,26 +195,76 @@ impl McServer for Vanilla {
     fn impl_update<'a>(&'a self) -> Option<&'a dyn McServerUpdate> {
         Some(self)
     }
+    fn impl_runtime<'a>(&'a self) -> Option<&'a dyn McServerRuntime> {
+        Some(self)
+    }
 }
 
 #[async_trait]
 impl McServerUpdate for Vanilla {
     async fn latest_version(&self) -> Result<McVersion> {
-        todo!()
+        let manifest = VersionManifest::fetch().await?;
+        Ok(parse_version(&manifest.latest.release, "vanilla"))
     }
 
     async fn install_version(&self, target: McVersion) -> Result<()> {
-        todo!()
+        let version = match target.channel {
+            McChannel::Release(major, minor, 0) => format!("{major}.{minor}"),
+            McChannel::Release(major, minor, patch) => format!("{major}.{minor}.{patch}"),
+            McChannel::Snapshot(v) => v,
+            McChannel::Unknown => return Err(anyhow!("Unknown target version")),
+        };
+        let manifest = VersionManifest::fetch().await?;
+        let entry = manifest
+            .versions
+            .iter()
+            .find(|v| v.id == version)
+            .context(format!("No such version: {version}"))?;
+        let text = Downloader::new()
+            .await
+            .get(entry.url.clone())
+            .await?
+            .with_successful_status()?
+            .text()
+            .await?;
+        let detail: VersionDetail = serde_json::from_str(&text)?;
+        let server = detail
+            .downloads
+            .server
+            .context(format!("Version {version} has no server download"))?;
+
+        info!("Installing vanilla {version}");
+        let checksum = server.sha1.map(Checksum::Sha1);
+        let file = Downloader::new()
+            .await
+            .download_verified(server.url, checksum.as_ref())
+            .await?;
+        // 保留旧的服务端
+        backup_server_file(&self.server_path).await?;
+        move_file(&file, &self.server_path).await?;
+        Ok(())
     }
 }
 
 #[async_trait]
 impl McServerRuntime for Vanilla {
     async fn ready_runtime(&self) -> Result<bool> {
-        todo!()
+        debug!("Check runtime");
+        let version = analyze_jar(&self.server_path)?.java_version as usize;
+        match GLOBAL_JAVA.check(version).await.into_iter().next() {
+            Some((java, _)) => {
+                self.runtime_path.store(Arc::new(java));
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
     async fn setup_runtime(&self) -> Result<()> {
-        todo!()
+        debug!("Install runtime");
+        let version = analyze_jar(&self.server_path)?.java_version as usize;
+        let java = GLOBAL_JAVA.install(version).await?;
+        self.runtime_path.store(Arc::new(java));
+        Ok(())
     }
     fn ext_script(&self, _: &str, os: &str) -> Result<String> {
         let mut s = String::new();
@@ -70,7 +276,7 @@ impl McServerRuntime for Vanilla {
         s.push_str(
             format!(
                 "{} -jar {} -nogui",
-                self.runtime_path.to_string_lossy(),
+                self.runtime_path.load().to_string_lossy(),
                 self.server_path.to_string_lossy()
             )
             .as_str(),
@@ -78,3 +284,117 @@ impl McServerRuntime for Vanilla {
         Ok(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::testing::{http_response, http_server, temp_dir};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const TTL: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn fresh_cache_skips_the_network() {
+        assert_eq!(
+            CachePlan::new(Some(Duration::from_secs(5)), TTL, false),
+            CachePlan::UseCache
+        );
+    }
+
+    #[test]
+    fn stale_or_missing_cache_is_fetched() {
+        assert_eq!(CachePlan::new(Some(TTL), TTL, false), CachePlan::Fetch);
+        assert_eq!(CachePlan::new(None, TTL, false), CachePlan::Fetch);
+        assert_eq!(
+            CachePlan::new(Some(Duration::from_secs(5)), Duration::ZERO, false),
+            CachePlan::Fetch
+        );
+    }
+
+    #[test]
+    fn offline_uses_any_cache() {
+        assert_eq!(
+            CachePlan::new(Some(TTL * 10), TTL, true),
+            CachePlan::UseCache
+        );
+        assert_eq!(CachePlan::new(None, TTL, true), CachePlan::Unavailable);
+    }
+
+    const MANIFEST: &str = r#"{"latest": {"release": "1.21.10", "snapshot": "25w41a"},
+        "versions": [{"id": "1.21.10", "type": "release", "url": "https://example.com/1.21.10.json"}]}"#;
+
+    /// 模拟的版本清单服务端，返回请求次数
+    async fn manifest_server() -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        let addr = http_server(Box::new(move |request| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Some(if request.contains("If-None-Match: \"v1\"") {
+                http_response("304 Not Modified", "", b"")
+            } else {
+                http_response("200 OK", "ETag: \"v1\"\r\n", MANIFEST.as_bytes())
+            })
+        }))
+        .await;
+        (format!("{addr}/version_manifest_v2.json"), requests)
+    }
+
+    async fn fetch(url: &str, dir: &Path, ttl: Duration, offline: bool) -> Result<VersionManifest> {
+        VersionManifest::fetch_from(Downloader::new().await, url, dir, ttl, offline).await
+    }
+
+    #[tokio::test]
+    async fn fetch_within_ttl_uses_the_cache() {
+        let (url, requests) = manifest_server().await;
+        let dir = temp_dir();
+        let manifest = fetch(&url, &dir, TTL, false).await.unwrap();
+        assert_eq!(manifest.latest.release, "1.21.10");
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("version_manifest_v2.etag")).unwrap(),
+            "\"v1\""
+        );
+
+        let manifest = fetch(&url, &dir, TTL, false).await.unwrap();
+        assert_eq!(manifest.versions[0].id, "1.21.10");
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_cache_is_revalidated() {
+        let (url, requests) = manifest_server().await;
+        let dir = temp_dir();
+        fetch(&url, &dir, TTL, false).await.unwrap();
+        // 过期后带 ETag 请求，304 时使用缓存
+        let manifest = fetch(&url, &dir, Duration::ZERO, false).await.unwrap();
+        assert_eq!(manifest.latest.release, "1.21.10");
+        assert_eq!(requests.load(Ordering::Relaxed), 2);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn unreachable_or_offline_falls_back_to_the_cache() {
+        let (url, requests) = manifest_server().await;
+        let dir = temp_dir();
+        assert!(fetch(&url, &dir, TTL, true).await.is_err());
+        assert_eq!(requests.load(Ordering::Relaxed), 0);
+
+        fetch(&url, &dir, TTL, false).await.unwrap();
+        let manifest = fetch(&url, &dir, Duration::ZERO, true).await.unwrap();
+        assert_eq!(manifest.latest.release, "1.21.10");
+        assert_eq!(requests.load(Ordering::Relaxed), 1);
+
+        // 端口 1 无法连接
+        let manifest = fetch(
+            "http://127.0.0.1:1/manifest.json",
+            &dir,
+            Duration::ZERO,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(manifest.latest.release, "1.21.10");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}